@@ -0,0 +1,319 @@
+use std::fmt::Display;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use toml_edit::{ImDocument, Item, TableLike};
+
+/// How bad a [`ConfigDiagnostic`] is.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The value couldn't be used, its default is used instead.
+    Error,
+    /// The value is ignored, like an unknown key.
+    Warning,
+}
+
+/// A problem found in the config file, see [`Config::diagnostics`](crate::Config::diagnostics).
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigDiagnostic {
+    pub severity: Severity,
+    pub message: String,
+    /// The config file the problem is in.
+    pub file: Option<PathBuf>,
+    /// 1-based line of the problem in [`Self::file`].
+    pub line: Option<usize>,
+    /// 1-based column of the problem in [`Self::file`], in characters.
+    pub column: Option<usize>,
+}
+
+impl Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let file = self.file.as_deref().and_then(Path::file_name);
+        if let Some(file) = file {
+            write!(f, "{}:", file.to_string_lossy())?;
+        }
+
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, "{line}:{column}:")?;
+        }
+
+        if file.is_some() || self.line.is_some() {
+            write!(f, " ")?;
+        }
+
+        write!(f, "{}", self.message)
+    }
+}
+
+/// A segment of the path to a value in the config file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum KeySegment {
+    Key(String),
+    Index(usize),
+}
+
+impl From<&str> for KeySegment {
+    fn from(value: &str) -> Self {
+        Self::Key(value.to_owned())
+    }
+}
+
+/// Converts the path of a key ignored while deserializing into `segments`.
+pub(crate) fn ignored_path(path: &serde_ignored::Path, segments: &mut Vec<KeySegment>) {
+    use serde_ignored::Path;
+
+    match path {
+        Path::Root => {}
+        Path::Seq { parent, index } => {
+            ignored_path(parent, segments);
+            segments.push(KeySegment::Index(*index));
+        }
+        Path::Map { parent, key } => {
+            ignored_path(parent, segments);
+            segments.push(KeySegment::Key(key.clone()));
+        }
+        Path::Some { parent }
+        | Path::NewtypeStruct { parent }
+        | Path::NewtypeVariant { parent } => ignored_path(parent, segments),
+    }
+}
+
+/// Formats `path` like `externalPlugins[0].exec`.
+pub(crate) fn display_path(path: &[KeySegment]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        match segment {
+            KeySegment::Key(key) if out.is_empty() => out.push_str(key),
+            KeySegment::Key(key) => {
+                out.push('.');
+                out.push_str(key);
+            }
+            KeySegment::Index(index) => out.push_str(&format!("[{index}]")),
+        }
+    }
+    out
+}
+
+/// The file a [`Config`](crate::Config) was loaded from, used to locate
+/// the problems found while loading it and the configs of its plugins.
+///
+/// Clones share the same problems, since plugins read their configs
+/// from clones of the loaded config.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConfigSource {
+    file: Option<PathBuf>,
+    toml: Arc<str>,
+    document: Option<Arc<ImDocument<String>>>,
+    diagnostics: Arc<Mutex<Vec<ConfigDiagnostic>>>,
+}
+
+impl ConfigSource {
+    pub fn new(file: Option<PathBuf>, toml: &str) -> Self {
+        Self {
+            file,
+            toml: toml.into(),
+            // documents that don't parse are reported by deserializing them
+            document: ImDocument::parse(toml.to_owned()).ok().map(Arc::new),
+            diagnostics: Default::default(),
+        }
+    }
+
+    /// Problems found so far, in the order they were found.
+    pub fn diagnostics(&self) -> Vec<ConfigDiagnostic> {
+        self.diagnostics.lock().unwrap().clone()
+    }
+
+    /// Records a problem at `span` in the file, unless it was already recorded,
+    /// since plugins read their config each time they are reloaded.
+    pub fn report(&self, severity: Severity, message: String, span: Option<Range<usize>>) {
+        let position = span.map(|span| position(&self.toml, span.start));
+
+        let diagnostic = ConfigDiagnostic {
+            severity,
+            message,
+            file: self.file.clone(),
+            line: position.map(|(line, _)| line),
+            column: position.map(|(_, column)| column),
+        };
+
+        let mut diagnostics = self.diagnostics.lock().unwrap();
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    /// Same as [`Self::report`] but locates the value at `path` in the file.
+    pub fn report_at(&self, severity: Severity, message: String, path: &[KeySegment]) {
+        let span = self.span(path);
+        self.report(severity, message, span);
+    }
+
+    /// Finds the key or array element at `path` in the file, falling back to
+    /// the closest parent that exists when the path doesn't.
+    fn span(&self, path: &[KeySegment]) -> Option<Range<usize>> {
+        let document = self.document.as_ref()?;
+
+        let mut node = Node::Item(document.as_item());
+        let mut span = None;
+
+        for segment in path {
+            let found = match segment {
+                KeySegment::Key(key) => node
+                    .table()
+                    .and_then(|t| t.get_key_value(key))
+                    .map(|(key, item)| (key.span().or_else(|| item.span()), Node::Item(item))),
+                KeySegment::Index(index) => node.element(*index),
+            };
+
+            let Some((found_span, found)) = found else {
+                break;
+            };
+
+            span = found_span.or(span);
+            node = found;
+        }
+
+        span
+    }
+}
+
+/// 1-based line and column of the character at byte `offset` of `text`.
+fn position(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map(|i| i + 1).unwrap_or(0);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// A value of the config file, arrays of tables hold
+/// tables and inline arrays hold values instead of items.
+enum Node<'a> {
+    Item(&'a Item),
+    Table(&'a toml_edit::Table),
+    Value(&'a toml_edit::Value),
+}
+
+impl<'a> Node<'a> {
+    fn table(&self) -> Option<&'a dyn TableLike> {
+        match *self {
+            Node::Item(item) => item.as_table_like(),
+            Node::Table(table) => Some(table),
+            Node::Value(value) => value.as_inline_table().map(|t| t as &dyn TableLike),
+        }
+    }
+
+    fn element(&self, index: usize) -> Option<(Option<Range<usize>>, Node<'a>)> {
+        let array = match *self {
+            Node::Item(Item::ArrayOfTables(tables)) => {
+                let table = tables.get(index)?;
+                return Some((table.span(), Node::Table(table)));
+            }
+            Node::Item(item) => item.as_array()?,
+            Node::Table(_) => return None,
+            Node::Value(value) => value.as_array()?,
+        };
+
+        let value = array.get(index)?;
+        Some((value.span(), Node::Value(value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::Config;
+
+    /// Line and column of each diagnostic, along with its severity.
+    fn positions(config: &Config) -> Vec<(Severity, Option<usize>, Option<usize>)> {
+        config
+            .diagnostics()
+            .into_iter()
+            .map(|d| (d.severity, d.line, d.column))
+            .collect()
+    }
+
+    #[test]
+    fn it_locates_syntax_errors() {
+        let toml = "[general]\nhotkey = \"Alt+Space\"\nmaxResults = \n";
+        let config = Config::from_toml_validated(toml, None);
+
+        assert_eq!(
+            positions(&config),
+            vec![(Severity::Error, Some(3), Some(14))]
+        );
+    }
+
+    #[test]
+    fn it_locates_unknown_nested_keys() {
+        let toml = "[general]\nhotkey = \"Alt+Space\"\n  unknown = true\n";
+        let config = Config::from_toml_validated(toml, None);
+
+        assert_eq!(
+            positions(&config),
+            vec![(Severity::Warning, Some(3), Some(3))]
+        );
+        assert!(config.diagnostics()[0]
+            .message
+            .contains("`general.unknown`"));
+    }
+
+    #[test]
+    fn it_locates_array_of_tables_elements() {
+        let toml = "[[externalPlugins]]\nexec = \"a.exe\"\n\n[[externalPlugins]]\nexec = \"b.exe\"\nunknown = 1\n";
+        let config = Config::from_toml_validated(toml, None);
+
+        assert_eq!(
+            positions(&config),
+            vec![(Severity::Warning, Some(6), Some(1))]
+        );
+        assert!(config.diagnostics()[0]
+            .message
+            .contains("`externalPlugins[1].unknown`"));
+
+        // keys that don't exist fall back to their element
+        let source = ConfigSource::new(None, toml);
+        let path = [
+            "externalPlugins".into(),
+            KeySegment::Index(1),
+            "missing".into(),
+        ];
+        source.report_at(Severity::Error, String::new(), &path);
+
+        let diagnostic = &source.diagnostics()[0];
+        assert_eq!((diagnostic.line, diagnostic.column), (Some(4), Some(1)));
+    }
+
+    #[test]
+    fn it_locates_invalid_plugin_fields() {
+        #[derive(Deserialize, Default)]
+        #[serde(deny_unknown_fields)]
+        struct PluginConfig {
+            #[allow(unused)]
+            paths: Vec<String>,
+        }
+
+        let toml = "[plugins.Test]\nenabled = true\npaths = [\"a\", 1]\n";
+        let config = Config::from_toml_validated(toml, None);
+        config.plugin_config::<PluginConfig>("Test");
+
+        assert_eq!(
+            positions(&config),
+            vec![(Severity::Error, Some(3), Some(15))]
+        );
+
+        let toml = "[plugins.Test]\nenabled = true\nunknown = 1\n";
+        let config = Config::from_toml_validated(toml, None);
+        config.plugin_config::<PluginConfig>("Test");
+
+        assert_eq!(
+            positions(&config),
+            vec![(Severity::Error, Some(3), Some(1))]
+        );
+    }
+}
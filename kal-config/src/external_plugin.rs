@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// An external plugin, a long-lived executable that kal talks to
+/// using JSON-RPC over its stdin and stdout.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalPluginConfig {
+    /// Path to the plugin executable.
+    pub exec: PathBuf,
+    /// Arguments passed to the plugin executable.
+    ///
+    /// Default: `[]`
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory of the plugin process.
+    ///
+    /// Default: the directory of the executable.
+    #[serde(alias = "working_directory", alias = "working-directory")]
+    pub working_directory: Option<PathBuf>,
+    /// Time in milliseconds to wait for a reply from the plugin process,
+    /// after which it is considered hung and restarted.
+    ///
+    /// Default: `5000`
+    #[serde(
+        default = "default_request_timeout",
+        alias = "request_timeout",
+        alias = "request-timeout"
+    )]
+    pub request_timeout: u64,
+}
+
+fn default_request_timeout() -> u64 {
+    5000
+}
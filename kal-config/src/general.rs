@@ -29,6 +29,50 @@ pub struct GeneralConfig {
         alias = "max-results"
     )]
     pub max_results: usize,
+    /// How much results that are launched often and recently are boosted
+    /// above their match score, `0` disables the boost.
+    ///
+    /// Default: `100`
+    #[serde(
+        default = "default_frecency_weight",
+        alias = "frecency_weight",
+        alias = "frecency-weight"
+    )]
+    pub frecency_weight: u16,
+    /// Number of days after which a launch counts half as much towards the boost,
+    /// lower values favor recent launches over frequent ones.
+    ///
+    /// Default: `7`
+    #[serde(
+        default = "default_frecency_half_life",
+        alias = "frecency_half_life",
+        alias = "frecency-half-life"
+    )]
+    pub frecency_half_life: f64,
+    /// Whether to also match names in other scripts, like Japanese, Chinese or Arabic,
+    /// by their latin transliteration, so `sushi` finds `すし`.
+    ///
+    /// Default: `false`
+    #[serde(default)]
+    pub transliterate: bool,
+    /// Pairs of keyboard layouts to also try the query in, for when it is typed
+    /// with the wrong layout active, for example `[["Russian", "English"]]`
+    /// finds `code` when typing `сщву`.
+    ///
+    /// Default: `[]`
+    #[serde(default, alias = "keyboard_layouts", alias = "keyboard-layouts")]
+    pub keyboard_layouts: Vec<[KeyboardLayout; 2]>,
+}
+
+/// A keyboard layout, see [`GeneralConfig::keyboard_layouts`].
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema)]
+pub enum KeyboardLayout {
+    /// US QWERTY.
+    English,
+    /// Russian ЙЦУКЕН.
+    Russian,
+    /// Arabic (101).
+    Arabic,
 }
 
 fn default_hotkey() -> String {
@@ -43,12 +87,24 @@ fn default_max_results() -> usize {
     24
 }
 
+fn default_frecency_weight() -> u16 {
+    100
+}
+
+fn default_frecency_half_life() -> f64 {
+    7.0
+}
+
 impl Default for GeneralConfig {
     fn default() -> Self {
         Self {
             hotkey: default_hotkey(),
             tab_through_action_buttons: default_true(),
             max_results: default_max_results(),
+            frecency_weight: default_frecency_weight(),
+            frecency_half_life: default_frecency_half_life(),
+            transliterate: false,
+            keyboard_layouts: Vec::new(),
         }
     }
 }
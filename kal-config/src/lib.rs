@@ -4,15 +4,23 @@ use std::path::{Path, PathBuf};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::diagnostic::{ConfigSource, KeySegment};
+
 mod appearance;
+mod diagnostic;
 mod error;
+mod external_plugin;
 mod general;
 mod plugin;
+mod schema;
 
 pub use appearance::*;
+pub use diagnostic::{ConfigDiagnostic, Severity};
 pub use error::*;
+pub use external_plugin::*;
 pub use general::*;
 pub use plugin::*;
+pub use schema::*;
 
 /// Kal configuration.
 #[derive(Serialize, Deserialize, Debug, Clone, Default, JsonSchema)]
@@ -27,6 +35,13 @@ pub struct Config {
     /// Plugins configuration.
     #[serde(default)]
     pub plugins: HashMap<String, PluginConfig>,
+    /// External plugins to spawn.
+    #[serde(default, alias = "external_plugins", alias = "external-plugins")]
+    pub external_plugins: Vec<ExternalPluginConfig>,
+
+    /// The file this config was loaded from, see [`Self::diagnostics`].
+    #[serde(skip)]
+    source: ConfigSource,
 }
 
 impl Config {
@@ -74,15 +89,100 @@ impl Config {
         Self::from_toml(&toml)
     }
 
-    /// Loads config from a canonical path, see [`Self::path`]
+    /// Loads config from a toml string, collecting the problems found
+    /// into [`Self::diagnostics`] instead of failing, unknown keys are reported as warnings.
+    ///
+    /// Falls back to the default config if `toml` isn't valid.
+    fn from_toml_validated(toml: &str, file: Option<PathBuf>) -> Self {
+        let span = tracing::debug_span!("config::from_toml_validated");
+        let _enter = span.enter();
+
+        let source = ConfigSource::new(file, toml);
+
+        let mut unknown_keys = Vec::new();
+        let deserializer = toml::Deserializer::new(toml);
+        let config = serde_ignored::deserialize(deserializer, |path| {
+            let mut segments = Vec::new();
+            diagnostic::ignored_path(&path, &mut segments);
+            unknown_keys.push(segments);
+        });
+
+        let mut config = config.unwrap_or_else(|e: toml::de::Error| {
+            let message = e.message().trim().replace('\n', ", ");
+            let message = format!("{message}, falling back to default config");
+            source.report(Severity::Error, message, e.span());
+            Self::default()
+        });
+
+        for path in unknown_keys {
+            let message = format!("Unknown key `{}`", diagnostic::display_path(&path));
+            source.report_at(Severity::Warning, message, &path);
+        }
+
+        config.source = source;
+        config
+    }
+
+    /// Loads config from a canonical path, see [`Self::path`],
+    /// falling back to default if it can't be read or isn't valid.
+    ///
+    /// Problems found in the file are logged and kept in [`Self::diagnostics`].
     pub fn load_with_fallback() -> Self {
-        Self::load()
-            .inspect_err(|e| tracing::error!("failed to load config, falling back to default: {e}"))
-            .unwrap_or_default()
+        let path = match Self::path() {
+            Ok(path) => path,
+            Err(e) => {
+                tracing::error!("failed to load config, falling back to default: {e}");
+                return Self::default();
+            }
+        };
+
+        let config = match std::fs::read_to_string(&path) {
+            Ok(toml) => Self::from_toml_validated(&toml, Some(path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                tracing::debug!("config not found, using default: {}", path.display());
+                Self::default()
+            }
+            Err(e) => {
+                let config = Self {
+                    source: ConfigSource::new(Some(path), ""),
+                    ..Default::default()
+                };
+                let message = format!("Failed to read config, falling back to default: {e}");
+                config.source.report(Severity::Error, message, None);
+                config
+            }
+        };
+
+        for diagnostic in config.diagnostics() {
+            match diagnostic.severity {
+                Severity::Error => tracing::error!("{diagnostic}"),
+                Severity::Warning => tracing::warn!("{diagnostic}"),
+            }
+        }
+
+        config
+    }
+
+    /// Problems found in the file this config was loaded from.
+    ///
+    /// Problems in the config of a plugin are only found once the plugin
+    /// reads it, see [`Self::plugin_config`].
+    pub fn diagnostics(&self) -> Vec<ConfigDiagnostic> {
+        self.source.diagnostics()
+    }
+
+    /// Reports a `[plugins]` entry that doesn't match any loaded plugin,
+    /// usually a typo in the plugin name, see [`Self::diagnostics`].
+    pub fn report_unknown_plugin(&self, name: &str) {
+        let message = format!("Unknown plugin `{name}`");
+        let path = ["plugins".into(), name.into()];
+        self.source.report_at(Severity::Warning, message, &path);
     }
 
     /// Gets the inner config for specified plugin,
     /// falling back to default if not found or failing to deserialize.
+    ///
+    /// Deserialize errors are kept in [`Self::diagnostics`].
     pub fn plugin_config<T>(&self, name: &str) -> T
     where
         T: Default,
@@ -92,14 +192,39 @@ impl Config {
             .get(name)
             .and_then(|c| c.inner.clone())
             .and_then(|c| {
-                toml::Table::try_into(c)
+                serde_path_to_error::deserialize(toml::Value::Table(c))
                     .inspect_err(|e| {
                         tracing::error!(
                             "Failed to deserialize {name} config, failling back to default: {e}"
                         );
+                        self.report_plugin_error(name, e);
                     })
                     .ok()
             })
             .unwrap_or_default()
     }
+
+    /// Reports an error deserializing the config of plugin `name`, see [`Self::plugin_config`].
+    fn report_plugin_error(&self, name: &str, error: &serde_path_to_error::Error<toml::de::Error>) {
+        use serde_path_to_error::Segment;
+
+        let mut path = vec![KeySegment::from("plugins"), KeySegment::from(name)];
+        path.extend(error.path().iter().filter_map(|segment| match segment {
+            Segment::Seq { index } => Some(KeySegment::Index(*index)),
+            Segment::Map { key } => Some(KeySegment::Key(key.clone())),
+            Segment::Enum { .. } | Segment::Unknown => None,
+        }));
+
+        // unknown fields are reported at their table, point at the field itself
+        let message = error.inner().message();
+        if let Some((field, _)) = message
+            .strip_prefix("unknown field `")
+            .and_then(|m| m.split_once('`'))
+        {
+            path.push(field.into());
+        }
+
+        let message = format!("Invalid `{name}` config, falling back to default: {message}");
+        self.source.report_at(Severity::Error, message, &path);
+    }
 }
@@ -18,6 +18,10 @@ pub struct PluginConfig {
         alias = "direct-activation-command"
     )]
     pub direct_activation_command: Option<String>,
+    /// Time in milliseconds to wait for this plugin's results on each query,
+    /// after which a warning is shown in its place.
+    #[serde(alias = "query_timeout", alias = "query-timeout")]
+    pub query_timeout: Option<u64>,
 
     /// An opaque type represnting plugin config options.
     #[serde(flatten)]
@@ -45,12 +49,19 @@ impl JsonSchema for PluginConfig {
                     "type": ["string", "null"],
                     "description": "Direct activation command for this plugin."
                 },
+                "queryTimeout": {
+                    "type": ["integer", "null"],
+                    "minimum": 0,
+                    "description": "Time in milliseconds to wait for this plugin's results on each query, after which a warning is shown in its place."
+                },
             }
         })
     }
 }
 
 impl PluginConfig {
+    const DEFAULT_QUERY_TIMEOUT: u64 = 500;
+
     /// Whether this plugin is enabled or not.
     ///
     /// Default: `true`
@@ -97,4 +108,22 @@ impl PluginConfig {
             .clone()
             .or_else(|| include.cloned())
     }
+
+    /// Time in milliseconds to wait for this plugin's results on each query.
+    ///
+    /// Default: `500`
+    pub fn query_timeout(&self) -> u64 {
+        self.query_timeout.unwrap_or(Self::DEFAULT_QUERY_TIMEOUT)
+    }
+
+    /// Time in milliseconds to wait for this plugin's results on each query.
+    ///
+    /// Falling back to provided default if `Some`.
+    ///
+    /// Default: `500`
+    pub fn query_timeout_or(&self, timeout: Option<u64>) -> u64 {
+        self.query_timeout
+            .or(timeout)
+            .unwrap_or(Self::DEFAULT_QUERY_TIMEOUT)
+    }
 }
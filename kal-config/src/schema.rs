@@ -0,0 +1,104 @@
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde_json::{Map, Value};
+
+use crate::{Config, PluginConfig};
+
+/// Builder for the JSON schema of [`Config`] that includes the options
+/// of each plugin, so editors can validate and complete `plugins.<name>` tables.
+pub struct ConfigSchema {
+    generator: SchemaGenerator,
+    plugins: Map<String, Value>,
+}
+
+impl Default for ConfigSchema {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConfigSchema {
+    pub fn new() -> Self {
+        Self {
+            generator: SchemaGenerator::default(),
+            plugins: Map::new(),
+        }
+    }
+
+    /// Adds the options of plugin `name`, where `T` is the type its
+    /// [`PluginConfig::inner`] options are deserialized into.
+    ///
+    /// The options every plugin has, like `enabled`, are added to `T`'s schema.
+    pub fn plugin<T: JsonSchema>(&mut self, name: &str) -> &mut Self {
+        let common = PluginConfig::json_schema(&mut self.generator);
+        let common = common
+            .get("properties")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+
+        let mut schema = self.generator.subschema_for::<T>();
+
+        // `T` is usually a reference to its definition, so the options are added there
+        let definition = schema
+            .get("$ref")
+            .and_then(Value::as_str)
+            .and_then(|r| r.rsplit('/').next())
+            .map(ToString::to_string);
+        let target = match definition {
+            Some(definition) => self
+                .generator
+                .definitions_mut()
+                .get_mut(&definition)
+                .and_then(Value::as_object_mut),
+            None => schema.as_object_mut(),
+        };
+
+        if let Some(target) = target {
+            let properties = target
+                .entry("properties")
+                .or_insert_with(|| Value::Object(Map::new()));
+            if let Value::Object(properties) = properties {
+                for (key, value) in common {
+                    // also the aliases `PluginConfig` accepts, since `T` usually denies unknown keys
+                    for alias in [snake_case(&key, '_'), snake_case(&key, '-')] {
+                        properties.entry(alias).or_insert_with(|| value.clone());
+                    }
+                    properties.entry(key).or_insert(value);
+                }
+            }
+        }
+
+        self.plugins.insert(name.to_string(), schema.to_value());
+
+        self
+    }
+
+    /// Generates the schema of [`Config`], with the options of every added plugin
+    /// under `plugins`, other plugins still accept the options every plugin has.
+    pub fn generate(mut self) -> Schema {
+        let mut schema = self.generator.root_schema_for::<Config>();
+
+        let plugins = schema
+            .get_mut("properties")
+            .and_then(|p| p.get_mut("plugins"))
+            .and_then(Value::as_object_mut);
+
+        if let Some(plugins) = plugins {
+            plugins.insert("properties".to_string(), Value::Object(self.plugins));
+        }
+
+        schema
+    }
+}
+
+/// Converts a camelCase `key` to snake_case, or kebab-case when `separator` is `-`.
+fn snake_case(key: &str, separator: char) -> String {
+    let mut out = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            out.push(separator);
+        }
+        out.push(c.to_ascii_lowercase());
+    }
+    out
+}
@@ -1,17 +1,110 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
 use serde::Serialize;
 
 use crate::{BuiltinIcon, Icon, ResultItem};
 
-type ActionFn = dyn Fn(&ResultItem) -> anyhow::Result<()> + Send + Sync;
+/// A future returned by running an [`Action`].
+pub type ActionFuture = Pin<Box<dyn Future<Output = anyhow::Result<ActionOutcome>> + Send>>;
+
+type SyncActionFn = dyn Fn(&ResultItem) -> anyhow::Result<ActionOutcome> + Send + Sync;
+type AsyncActionFn = dyn Fn(&ResultItem, ActionProgress) -> ActionFuture + Send + Sync;
+
+enum ActionFn {
+    /// Runs inline when invoked, should be quick.
+    Sync(Box<SyncActionFn>),
+    /// Returns a future that runs on the async executor and can report its progress.
+    Async(Box<AsyncActionFn>),
+    /// Run by the plugin that produced the item, see [`Action::dispatch`],
+    /// `is_async` if it is long running, see [`Action::dispatch_async`].
+    Dispatch { is_async: bool },
+}
+
+/// Wraps the result of work that already finished into an [`ActionFuture`].
+pub fn ready_outcome<R: Into<ActionOutcome>>(result: anyhow::Result<R>) -> ActionFuture {
+    Box::pin(std::future::ready(result.map(Into::into)))
+}
+
+/// Reports the progress of a running async [`Action`] to the main window.
+#[derive(Clone)]
+pub struct ActionProgress {
+    report: Arc<dyn Fn(String) + Send + Sync>,
+}
+
+impl std::fmt::Debug for ActionProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActionProgress").finish_non_exhaustive()
+    }
+}
+
+impl Default for ActionProgress {
+    fn default() -> Self {
+        Self::new(|_| {})
+    }
+}
+
+impl ActionProgress {
+    pub fn new<F>(report: F) -> Self
+    where
+        F: Fn(String) + 'static + Send + Sync,
+    {
+        Self {
+            report: Arc::new(report),
+        }
+    }
+
+    /// Reports a progress message, like the step currently running.
+    pub fn report(&self, message: impl Into<String>) {
+        (self.report)(message.into())
+    }
+}
+
+/// What should happen after an [`Action`] runs.
+///
+/// Actions returning `()` hide the main window.
+#[derive(Debug, Default)]
+pub enum ActionOutcome {
+    /// Hide the main window.
+    #[default]
+    Hide,
+    /// Keep the main window open as is.
+    KeepOpen,
+    /// Keep the main window open and replace the search text
+    /// with this query, for example to drill into a folder.
+    SetQuery(String),
+    /// Keep the main window open and replace the results with these
+    /// follow-up items, shown in the same order.
+    ReplaceResults(Vec<ResultItem>),
+    /// Keep the main window open and briefly show this message.
+    ShowMessage(String),
+}
+
+impl From<()> for ActionOutcome {
+    fn from(_: ()) -> Self {
+        ActionOutcome::Hide
+    }
+}
 
 #[derive(Serialize)]
 pub struct Action {
-    pub id: &'static str,
+    /// Identifies the action within its item, can be generated at runtime,
+    /// for example one action per installed editor.
+    pub id: Cow<'static, str>,
     pub icon: Option<Icon>,
-    pub description: Option<&'static str>,
-    pub accelerator: Option<&'static str>,
+    pub description: Option<Cow<'static, str>>,
+    pub accelerator: Option<Cow<'static, str>>,
+    /// Text of a confirmation dialog shown before running the action.
+    #[serde(skip)]
+    pub confirmation: Option<Cow<'static, str>>,
+    /// Secondary actions are shown in ascending order,
+    /// actions with the same order keep the order they were added in.
+    #[serde(skip)]
+    pub order: i32,
     #[serde(skip)]
-    pub action: Box<ActionFn>,
+    action: ActionFn,
 }
 
 impl std::fmt::Debug for Action {
@@ -20,23 +113,83 @@ impl std::fmt::Debug for Action {
             .field("id", &self.id)
             .field("description", &self.description)
             .field("accelerator", &self.accelerator)
+            .field("confirmation", &self.confirmation)
+            .field("order", &self.order)
             .field("action", &"<action>")
             .finish()
     }
 }
 
 impl Action {
-    pub fn new<F>(id: &'static str, action: F) -> Self
-    where
-        F: Fn(&ResultItem) -> anyhow::Result<()> + 'static + Send + Sync,
-    {
+    pub const PRIMARY: &'static str = "RunPrimary";
+    pub const RUN_ELEVATED: &'static str = "RunElevated";
+    pub const OPEN_LOCATION: &'static str = "OpenLocation";
+
+    fn with_fn(id: impl Into<Cow<'static, str>>, action: ActionFn) -> Self {
         Self {
-            id,
+            id: id.into(),
             icon: None,
             description: None,
             accelerator: None,
-            action: Box::new(action),
+            confirmation: None,
+            order: 0,
+            action,
         }
+        .with_builtin_defaults()
+    }
+
+    /// Sets the icon, description and accelerator of builtin actions, like [`Action::PRIMARY`].
+    fn with_builtin_defaults(self) -> Self {
+        match self.id.as_ref() {
+            Self::PRIMARY => self.with_accelerator("Enter"),
+            Self::RUN_ELEVATED => self
+                .with_icon(BuiltinIcon::Admin.into())
+                .with_description("Run as adminstrator")
+                .with_accelerator("Shift+Enter"),
+            Self::OPEN_LOCATION => self
+                .with_icon(BuiltinIcon::FolderOpen.into())
+                .with_description("Open containing folder")
+                .with_accelerator("Ctrl+O"),
+            _ => self,
+        }
+    }
+
+    pub fn new<F, R>(id: impl Into<Cow<'static, str>>, action: F) -> Self
+    where
+        F: Fn(&ResultItem) -> anyhow::Result<R> + 'static + Send + Sync,
+        R: Into<ActionOutcome>,
+    {
+        let action = move |item: &ResultItem| action(item).map(Into::into);
+        Self::with_fn(id, ActionFn::Sync(Box::new(action)))
+    }
+
+    /// Creates an action that runs on the async executor instead of blocking it,
+    /// for long running work, reporting its progress through [`ActionProgress`].
+    pub fn new_async<F, Fut, R>(id: impl Into<Cow<'static, str>>, action: F) -> Self
+    where
+        F: Fn(&ResultItem, ActionProgress) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = anyhow::Result<R>> + 'static + Send,
+        R: Into<ActionOutcome>,
+    {
+        let action = move |item: &ResultItem, progress| {
+            let future = action(item, progress);
+            Box::pin(async move { future.await.map(Into::into) }) as ActionFuture
+        };
+
+        Self::with_fn(id, ActionFn::Async(Box::new(action)))
+    }
+
+    /// Creates an action that doesn't capture anything and is run by the plugin
+    /// that produced its item through [`Plugin::run_action`](crate::Plugin::run_action),
+    /// using the item's [`ResultItem::payload`].
+    pub fn dispatch(id: impl Into<Cow<'static, str>>) -> Self {
+        Self::with_fn(id, ActionFn::Dispatch { is_async: false })
+    }
+
+    /// Same as [`Action::dispatch`] but for long running work, like [`Action::new_async`],
+    /// the main window shows a spinner and the progress it reports while it runs.
+    pub fn dispatch_async(id: impl Into<Cow<'static, str>>) -> Self {
+        Self::with_fn(id, ActionFn::Dispatch { is_async: true })
     }
 
     pub fn with_icon(mut self, icon: Icon) -> Self {
@@ -44,46 +197,98 @@ impl Action {
         self
     }
 
-    pub fn with_description(mut self, description: &'static str) -> Self {
-        self.description = Some(description);
+    pub fn with_description(mut self, description: impl Into<Cow<'static, str>>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_accelerator(mut self, accelerator: impl Into<Cow<'static, str>>) -> Self {
+        self.accelerator = Some(accelerator.into());
         self
     }
 
-    pub fn with_accelerator(mut self, accelerator: &'static str) -> Self {
-        self.accelerator = Some(accelerator);
+    /// Asks the user to confirm before running this action, for destructive actions.
+    pub fn with_confirmation(mut self, confirmation: impl Into<Cow<'static, str>>) -> Self {
+        self.confirmation = Some(confirmation.into());
         self
     }
 
-    pub fn run(&self, item: &ResultItem) -> anyhow::Result<()> {
-        (self.action)(item)
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// Whether this action was created using [`Action::new_async`] or [`Action::dispatch_async`].
+    pub fn is_async(&self) -> bool {
+        matches!(
+            self.action,
+            ActionFn::Async(_) | ActionFn::Dispatch { is_async: true }
+        )
+    }
+
+    /// Whether this action was created using [`Action::dispatch`] or [`Action::dispatch_async`].
+    pub fn is_dispatched(&self) -> bool {
+        matches!(self.action, ActionFn::Dispatch { .. })
+    }
+
+    /// Runs this action, sync actions run immediately and
+    /// the returned future is already complete.
+    ///
+    /// Dispatched actions can't run on their own and fail,
+    /// they need to be run by their plugin instead.
+    pub fn run(&self, item: &ResultItem, progress: ActionProgress) -> ActionFuture {
+        match &self.action {
+            ActionFn::Sync(action) => ready_outcome(action(item)),
+            ActionFn::Async(action) => action(item, progress),
+            ActionFn::Dispatch { .. } => ready_outcome::<ActionOutcome>(Err(anyhow::anyhow!(
+                "`{}` must be run by the plugin that produced `{}`",
+                self.id,
+                item.id
+            ))),
+        }
     }
 }
 
 impl Action {
-    pub fn primary<F>(action: F) -> Self
+    pub fn primary<F, R>(action: F) -> Self
+    where
+        F: Fn(&ResultItem) -> anyhow::Result<R> + 'static + Send + Sync,
+        R: Into<ActionOutcome>,
+    {
+        Self::new(Self::PRIMARY, action)
+    }
+
+    pub fn open_elevated<F, R>(action: F) -> Self
+    where
+        F: Fn(&ResultItem) -> anyhow::Result<R> + 'static + Send + Sync,
+        R: Into<ActionOutcome>,
+    {
+        Self::new(Self::RUN_ELEVATED, action)
+    }
+
+    pub fn primary_async<F, Fut, R>(action: F) -> Self
     where
-        F: Fn(&ResultItem) -> anyhow::Result<()> + 'static + Send + Sync,
+        F: Fn(&ResultItem, ActionProgress) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = anyhow::Result<R>> + 'static + Send,
+        R: Into<ActionOutcome>,
     {
-        Self::new("RunPrimary", action).with_accelerator("Enter")
+        Self::new_async(Self::PRIMARY, action)
     }
 
-    pub fn open_elevated<F>(action: F) -> Self
+    pub fn open_elevated_async<F, Fut, R>(action: F) -> Self
     where
-        F: Fn(&ResultItem) -> anyhow::Result<()> + 'static + Send + Sync,
+        F: Fn(&ResultItem, ActionProgress) -> Fut + 'static + Send + Sync,
+        Fut: Future<Output = anyhow::Result<R>> + 'static + Send,
+        R: Into<ActionOutcome>,
     {
-        Self::new("RunElevated", action)
-            .with_icon(BuiltinIcon::Admin.into())
-            .with_description("Run as adminstrator")
-            .with_accelerator("Shift+Enter")
+        Self::new_async(Self::RUN_ELEVATED, action)
     }
 
-    pub fn open_location<F>(action: F) -> Self
+    pub fn open_location<F, R>(action: F) -> Self
     where
-        F: Fn(&ResultItem) -> anyhow::Result<()> + 'static + Send + Sync,
+        F: Fn(&ResultItem) -> anyhow::Result<R> + 'static + Send + Sync,
+        R: Into<ActionOutcome>,
     {
-        Self::new("OpenLocation", action)
-            .with_icon(BuiltinIcon::FolderOpen.into())
-            .with_description("Open containing folder")
-            .with_accelerator("Ctrl+O")
+        Self::new(Self::OPEN_LOCATION, action)
     }
 }
@@ -0,0 +1,55 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use event_listener::Event;
+
+/// A token passed to [`Plugin::query`](crate::Plugin::query) that is cancelled
+/// once a newer query starts, so plugins can stop working on outdated queries.
+///
+/// Cloning the token is cheap and all clones share the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    cancelled: AtomicBool,
+    event: Event,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels this token and wakes up everyone waiting on [`Self::cancelled`].
+    pub fn cancel(&self) {
+        if !self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            self.inner.event.notify(usize::MAX);
+        }
+    }
+
+    /// Whether this token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Waits until this token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            if self.is_cancelled() {
+                return;
+            }
+
+            let listener = self.inner.event.listen();
+
+            // check again in case it was cancelled before the listener was registered
+            if self.is_cancelled() {
+                return;
+            }
+
+            listener.await;
+        }
+    }
+}
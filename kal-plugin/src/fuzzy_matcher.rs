@@ -1,20 +1,529 @@
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use nucleo::pattern::{AtomKind, CaseMatching, Normalization, Pattern};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Fuzzy matches queries against the texts of results.
+///
+/// Each whitespace separated word of a query is matched on its own, so `vs code`
+/// matches `Visual Studio Code`, and queries spelling the initials of the words
+/// in a text, like `vsc`, are boosted above scattered matches.
+///
+/// Accents are ignored, so `uber` matches `Über`, and texts in other scripts can also
+/// be matched by their latin transliteration, see [`Self::set_transliterate`].
 #[derive(Default, Debug)]
 pub struct FuzzyMatcher {
     inner: nucleo::Matcher,
+    /// The needle `pattern` was parsed from, the same needle is usually
+    /// matched against many haystacks so it is only parsed again when it changes.
+    needle: String,
+    pattern: Pattern,
+    /// `pattern` parsed from the folded needle, matched against folded haystacks.
+    folded_pattern: Pattern,
+    transliterate: bool,
+    /// Folded haystacks, the same haystacks are matched on every query
+    /// so they are only folded once, `None` if folding doesn't change them.
+    folded: HashMap<Box<str>, Option<Arc<Folded>>>,
+    indices_buf: Vec<u32>,
+    /// Items that matched recent needles, see [`Self::candidates`].
+    ///
+    /// Each chain holds the needles typed one after the other, each extending the previous one,
+    /// more than one chain is kept since the same matcher can also be used for rewritten queries.
+    candidates: Vec<Vec<CachedMatches>>,
+}
+
+/// Indices of the items out of `len` that matched `needle`, see [`FuzzyMatcher::cache_matches`].
+#[derive(Debug)]
+struct CachedMatches {
+    needle: String,
+    len: usize,
+    matches: Vec<usize>,
+}
+
+impl CachedMatches {
+    /// Whether every item that matches `needle` is one of `self.matches`,
+    /// which is the case when `needle` extends the cached needle.
+    fn narrows_to(&self, needle: &str, len: usize) -> bool {
+        // a trailing `\` escapes the whitespace typed after it,
+        // turning two words into one that may not match anymore
+        self.len == len
+            && !self.needle.is_empty()
+            && !self.needle.ends_with('\\')
+            && needle.starts_with(&self.needle)
+    }
+}
+
+/// A successful match returned by [`FuzzyMatcher::fuzzy_indices`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: u16,
+    /// Ranges of matched characters in the haystack, sorted and non overlapping.
+    pub ranges: Vec<Range<usize>>,
 }
 
 impl FuzzyMatcher {
+    /// Added to the score for each character of a needle that spells the initials of a haystack.
+    const ACRONYM_BONUS: u16 = 16;
+    /// Max number of folded haystacks kept around, plugins like `Everything`
+    /// return different haystacks for every query so the cache is cleared once it is full.
+    const MAX_FOLDED: usize = 10_000;
+    /// Max number of chains of cached matches, see [`Self::candidates`].
+    const MAX_CANDIDATE_CHAINS: usize = 4;
+
+    /// Whether to also match texts in other scripts, like Japanese or Arabic,
+    /// against their latin transliteration, so `sushi` matches `すし`.
+    pub fn set_transliterate(&mut self, transliterate: bool) {
+        if self.transliterate != transliterate {
+            self.transliterate = transliterate;
+            self.folded.clear();
+            self.candidates.clear();
+            // parse the folded pattern again on the next match
+            self.needle.clear();
+            self.pattern = Pattern::default();
+            self.folded_pattern = Pattern::default();
+        }
+    }
+
+    /// Returns the indices of the items, out of `len` items, that can match `needle`.
+    ///
+    /// When `needle` extends a needle whose matches were cached using [`Self::cache_matches`],
+    /// like `visu` after `vis`, only the items that matched it can match `needle`
+    /// so only those are returned, otherwise all of them are. Removing characters
+    /// from the needle goes back to the matches cached for the shorter needle.
+    ///
+    /// Plugins matching a lot of items use this to only match the candidates on each keystroke,
+    /// their items must only match a needle if they also match every needle it extends.
+    pub fn candidates(&mut self, needle: &str, len: usize) -> Vec<usize> {
+        let best = self
+            .candidates
+            .iter()
+            .enumerate()
+            .flat_map(|(chain_idx, chain)| {
+                chain
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, cached)| cached.narrows_to(needle, len))
+                    .map(move |(idx, cached)| (chain_idx, idx, cached.needle.len()))
+            })
+            .max_by_key(|(_, _, needle_len)| *needle_len);
+
+        let Some((chain_idx, idx, _)) = best else {
+            return (0..len).collect();
+        };
+
+        // needles typed after the one narrowing `needle` don't apply anymore,
+        // and the chain is moved last so it is the last one evicted
+        let mut chain = self.candidates.remove(chain_idx);
+        chain.truncate(idx + 1);
+        let candidates = chain[idx].matches.clone();
+        self.candidates.push(chain);
+
+        candidates
+    }
+
+    /// Caches the indices of the items, out of `len` items, that matched `needle`,
+    /// so the next needles extending it only match those, see [`Self::candidates`].
+    pub fn cache_matches(&mut self, needle: &str, len: usize, matches: Vec<usize>) {
+        if needle.is_empty() {
+            return;
+        }
+
+        let cached = CachedMatches {
+            needle: needle.to_owned(),
+            len,
+            matches,
+        };
+
+        let chain = self.candidates.iter_mut().rev().find(|chain| {
+            chain
+                .last()
+                .is_some_and(|last| last.needle == needle || last.narrows_to(needle, len))
+        });
+
+        match chain {
+            Some(chain) => {
+                if chain.last().is_some_and(|last| last.needle == needle) {
+                    chain.pop();
+                }
+                chain.push(cached);
+            }
+            None => {
+                if self.candidates.len() >= Self::MAX_CANDIDATE_CHAINS {
+                    self.candidates.remove(0);
+                }
+                self.candidates.push(vec![cached]);
+            }
+        }
+    }
+
+    /// Forgets the cached matches, for when the items being matched have changed.
+    pub fn clear_candidates(&mut self) {
+        self.candidates.clear();
+    }
+
+    fn update_pattern(&mut self, needle: &str) {
+        if self.needle != needle {
+            self.pattern = parse_pattern(needle);
+            self.folded_pattern = match fold(needle, self.transliterate) {
+                Some(folded) => parse_pattern(&folded.text),
+                None => self.pattern.clone(),
+            };
+            self.needle = needle.to_owned();
+        }
+    }
+
+    fn folded(&mut self, haystack: &str) -> Option<Arc<Folded>> {
+        if haystack.is_ascii() {
+            return None;
+        }
+
+        if let Some(folded) = self.folded.get(haystack) {
+            return folded.clone();
+        }
+
+        if self.folded.len() >= Self::MAX_FOLDED {
+            self.folded.clear();
+        }
+
+        let folded = fold(haystack, self.transliterate).map(Arc::new);
+        self.folded.insert(haystack.into(), folded.clone());
+        folded
+    }
+
     pub fn fuzzy_match(&mut self, haystack: &str, needle: &str) -> Option<u16> {
-        // TODO: Consider finding an alternative fuzzy matching library that
-        // doesn't panic on uppercase characters.
-        let needle = needle.to_lowercase();
+        self.update_pattern(needle);
 
         let mut haystack_buf = Vec::new();
-        let mut needle_buf = Vec::new();
 
-        let haystack = nucleo::Utf32Str::new(haystack, &mut haystack_buf);
-        let needle = nucleo::Utf32Str::new(needle.as_str(), &mut needle_buf);
+        let haystack_str = nucleo::Utf32Str::new(haystack, &mut haystack_buf);
+        let score = self
+            .pattern
+            .score(haystack_str, &mut self.inner)
+            .map(|score| total_score(score, haystack, needle));
+
+        let folded_score = self.folded(haystack).and_then(|folded| {
+            let folded_str = nucleo::Utf32Str::new(&folded.text, &mut haystack_buf);
+            self.folded_pattern
+                .score(folded_str, &mut self.inner)
+                .map(|score| total_score(score, &folded.text, needle))
+        });
+
+        score.max(folded_score)
+    }
+
+    /// Matches `needle` against each of `haystacks`, returning the best score.
+    pub fn fuzzy_match_any<S: AsRef<str>>(
+        &mut self,
+        haystacks: impl IntoIterator<Item = S>,
+        needle: &str,
+    ) -> Option<u16> {
+        haystacks
+            .into_iter()
+            .filter_map(|haystack| self.fuzzy_match(haystack.as_ref(), needle))
+            .max()
+    }
+
+    /// Same as [`Self::fuzzy_match`] but also returns which characters of `haystack` matched,
+    /// so they can be highlighted.
+    pub fn fuzzy_indices(&mut self, haystack: &str, needle: &str) -> Option<FuzzyMatch> {
+        self.update_pattern(needle);
+
+        let matched = self
+            .match_indices(haystack, false)
+            .map(|(score, ranges)| FuzzyMatch {
+                score: total_score(score, haystack, needle),
+                ranges,
+            });
+
+        let folded_matched = self.folded(haystack).and_then(|folded| {
+            let (score, ranges) = self.match_indices(&folded.text, true)?;
+            Some(FuzzyMatch {
+                score: total_score(score, &folded.text, needle),
+                ranges: folded.origin_ranges(&ranges),
+            })
+        });
+
+        // prefer the original haystack on ties, its ranges map exactly to what is shown
+        [matched, folded_matched]
+            .into_iter()
+            .flatten()
+            .reduce(|best, m| if m.score > best.score { m } else { best })
+    }
+
+    /// Matches the needle, or the folded needle if `folded` is set, against `text`,
+    /// returning the score and the ranges of matched characters.
+    fn match_indices(&mut self, text: &str, folded: bool) -> Option<(u32, Vec<Range<usize>>)> {
+        // nucleo matches the first char of each grapheme and returns grapheme indices,
+        // keep the graphemes around to map them back to char indices
+        let graphemes = if text.is_ascii() {
+            None
+        } else {
+            Some(text.graphemes(true).collect::<Vec<_>>())
+        };
+
+        let text_buf = match &graphemes {
+            Some(graphemes) => graphemes.iter().filter_map(|g| g.chars().next()).collect(),
+            None => Vec::new(),
+        };
+
+        let text_str = match &graphemes {
+            Some(_) => nucleo::Utf32Str::Unicode(&text_buf),
+            None => nucleo::Utf32Str::Ascii(text.as_bytes()),
+        };
+
+        let pattern = if folded {
+            &self.folded_pattern
+        } else {
+            &self.pattern
+        };
+
+        self.indices_buf.clear();
+        let score = pattern.indices(text_str, &mut self.inner, &mut self.indices_buf)?;
+
+        self.indices_buf.sort_unstable();
+        self.indices_buf.dedup();
+
+        Some((score, char_ranges(graphemes.as_deref(), &self.indices_buf)))
+    }
+}
+
+fn parse_pattern(needle: &str) -> Pattern {
+    Pattern::new(
+        needle,
+        CaseMatching::Ignore,
+        Normalization::Smart,
+        AtomKind::Fuzzy,
+    )
+}
+
+/// Adds the acronym bonus to the score of a match.
+///
+/// Scores of multiple words are summed up, which can overflow the score of a single match.
+fn total_score(score: u32, haystack: &str, needle: &str) -> u16 {
+    (score.min(u16::MAX as u32) as u16).saturating_add(acronym_bonus(haystack, needle))
+}
+
+/// Bonus for needles spelling the first characters of the words in `haystack`,
+/// like `vsc` for `Visual Studio Code` or `gh` for `GitHub`.
+fn acronym_bonus(haystack: &str, needle: &str) -> u16 {
+    let needle = needle
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect::<String>();
+
+    // a single character is the initial of too many haystacks to mean anything
+    if needle.chars().count() < 2 {
+        return 0;
+    }
+
+    let mut prev: Option<char> = None;
+    let initials = haystack
+        .chars()
+        .filter(|&c| {
+            let initial = c.is_alphanumeric()
+                && prev.is_none_or(|prev| {
+                    !prev.is_alphanumeric() || (prev.is_lowercase() && c.is_uppercase())
+                });
+            prev = Some(c);
+            initial
+        })
+        .flat_map(char::to_lowercase)
+        .collect::<String>();
+
+    if initials.starts_with(&needle) {
+        FuzzyMatcher::ACRONYM_BONUS.saturating_mul(needle.chars().count() as u16)
+    } else {
+        0
+    }
+}
+
+/// A text with its accents removed and optionally transliterated, see [`fold`].
+#[derive(Debug)]
+struct Folded {
+    text: String,
+    /// Index of the character in the original text each character of `text` came from.
+    origins: Vec<usize>,
+}
+
+impl Folded {
+    /// Maps ranges of characters in the folded text back to the original text.
+    fn origin_ranges(&self, ranges: &[Range<usize>]) -> Vec<Range<usize>> {
+        let mut origin_ranges = Vec::new();
+
+        for index in ranges.iter().cloned().flatten() {
+            if let Some(&origin) = self.origins.get(index) {
+                push_range(&mut origin_ranges, origin..origin + 1);
+            }
+        }
+
+        origin_ranges
+    }
+}
+
+/// Folds accents and compatibility characters of `text`, so `Über` becomes `Uber`
+/// and `ｆｕｌｌ` becomes `full`, and if `transliterate` is set also spells
+/// other scripts in latin, so `すし` becomes `sushi`.
+///
+/// Returns `None` if folding doesn't change `text`.
+fn fold(text: &str, transliterate: bool) -> Option<Folded> {
+    if text.is_ascii() {
+        return None;
+    }
+
+    let mut folded = Folded {
+        text: String::with_capacity(text.len()),
+        origins: Vec::with_capacity(text.len()),
+    };
+
+    let mut decomposed = String::new();
+
+    for (idx, c) in text.chars().enumerate() {
+        decomposed.clear();
+
+        if c.is_ascii() {
+            decomposed.push(c);
+        } else {
+            unicode_normalization::char::decompose_compatible(c, |c| {
+                if !unicode_normalization::char::is_combining_mark(c) {
+                    decomposed.push(c);
+                }
+            });
+
+            if transliterate && !decomposed.is_ascii() {
+                decomposed.clear();
+                decomposed.push_str(any_ascii::any_ascii_char(c));
+            }
+        }
+
+        for c in decomposed.chars() {
+            folded.text.push(c);
+            folded.origins.push(idx);
+        }
+    }
+
+    (folded.text != text).then_some(folded)
+}
+
+/// Pushes `range` to `ranges`, merging it with the last range if they touch or overlap.
+fn push_range(ranges: &mut Vec<Range<usize>>, range: Range<usize>) {
+    match ranges.last_mut() {
+        Some(last) if last.end >= range.start => last.end = last.end.max(range.end),
+        _ => ranges.push(range),
+    }
+}
+
+/// Converts the sorted indices returned by nucleo into merged ranges of characters,
+/// `graphemes` is `None` for ascii haystacks where each index is a single character.
+fn char_ranges(graphemes: Option<&[&str]>, indices: &[u32]) -> Vec<Range<usize>> {
+    let offsets = graphemes.map(|graphemes| {
+        let mut start = 0;
+        graphemes
+            .iter()
+            .map(|g| {
+                let range = start..start + g.chars().count();
+                start = range.end;
+                range
+            })
+            .collect::<Vec<_>>()
+    });
+
+    let mut ranges = Vec::new();
+
+    for &index in indices {
+        let index = index as usize;
+        let range = match &offsets {
+            Some(offsets) => match offsets.get(index) {
+                Some(range) => range.clone(),
+                None => continue,
+            },
+            None => index..index + 1,
+        };
+
+        push_range(&mut ranges, range);
+    }
+
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merges_consecutive_indices() {
+        let mut matcher = FuzzyMatcher::default();
+        let m = matcher
+            .fuzzy_indices("Visual Studio Code", "vscode")
+            .unwrap();
+        assert_eq!(m.ranges, vec![0..1, 7..8, 14..18]);
+    }
+
+    #[test]
+    fn matches_each_word() {
+        let mut matcher = FuzzyMatcher::default();
+        assert!(matcher
+            .fuzzy_match("Visual Studio Code", "vs code")
+            .is_some());
+        assert!(matcher
+            .fuzzy_match("Visual Studio Code", "code vs")
+            .is_some());
+        assert!(matcher.fuzzy_match("Visual Studio", "vs code").is_none());
+    }
+
+    #[test]
+    fn boosts_acronyms() {
+        let mut matcher = FuzzyMatcher::default();
+        let acronym = matcher.fuzzy_match("Visual Studio Code", "vsc").unwrap();
+        let scattered = matcher.fuzzy_match("Visual Basic Script", "vsc").unwrap();
+        assert!(acronym > scattered);
+        assert!(matcher.fuzzy_match("GitHub Desktop", "ghd").unwrap() > 0);
+    }
+
+    #[test]
+    fn maps_graphemes_to_chars() {
+        let mut matcher = FuzzyMatcher::default();
+        // `e\u{301}` is a single grapheme made of two chars
+        let m = matcher.fuzzy_indices("cafe\u{301} bar", "bar").unwrap();
+        assert_eq!(m.ranges, vec![6..9]);
+    }
+
+    #[test]
+    fn ignores_accents() {
+        let mut matcher = FuzzyMatcher::default();
+        let m = matcher.fuzzy_indices("Übersicht", "uber").unwrap();
+        assert_eq!(m.ranges, vec![0..4]);
+        assert!(matcher
+            .fuzzy_match("Crème brûlée", "creme brulee")
+            .is_some());
+        assert!(matcher.fuzzy_match("ｆｕｌｌ", "full").is_some());
+    }
+
+    #[test]
+    fn transliterates() {
+        let mut matcher = FuzzyMatcher::default();
+        assert!(matcher.fuzzy_match("すし", "sushi").is_none());
+
+        matcher.set_transliterate(true);
+        let m = matcher.fuzzy_indices("すし 店", "sushi").unwrap();
+        assert_eq!(m.ranges, vec![0..2]);
+        assert!(matcher.fuzzy_match("مرحبا", "mrhb").is_some());
+    }
+
+    #[test]
+    fn narrows_candidates() {
+        let mut matcher = FuzzyMatcher::default();
+        assert_eq!(matcher.candidates("vis", 4), vec![0, 1, 2, 3]);
+        matcher.cache_matches("vis", 4, vec![0, 2, 3]);
+        matcher.cache_matches("visu", 4, vec![2, 3]);
 
-        self.inner.fuzzy_match(haystack, needle)
+        assert_eq!(matcher.candidates("visua", 4), vec![2, 3]);
+        assert_eq!(matcher.candidates("vis", 4), vec![0, 2, 3]);
+        // `visu` was dropped when going back to `vis`
+        assert_eq!(matcher.candidates("visu", 4), vec![0, 2, 3]);
+        assert_eq!(matcher.candidates("code", 4), vec![0, 1, 2, 3]);
+        assert_eq!(matcher.candidates("visu", 5), vec![0, 1, 2, 3, 4]);
     }
 }
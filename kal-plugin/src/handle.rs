@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use serde::Serialize;
+
+use crate::ResultItem;
+
+type EventFn = dyn Fn(&str, PluginEvent) + Send + Sync;
+
+/// Something a plugin tells the main window outside of queries, see [`PluginHandle`].
+#[derive(Debug)]
+pub enum PluginEvent {
+    /// Progress of indexing in the background, see [`Index::reindex`](crate::Index::reindex).
+    Indexing(IndexingStatus),
+    /// See [`PluginHandle::requery`].
+    Requery,
+    /// See [`PluginHandle::push_results`].
+    PushResults(Vec<ResultItem>),
+}
+
+/// Progress of a plugin indexing in the background.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "state")]
+pub enum IndexingStatus {
+    /// Still indexing, with the number of items scanned so far.
+    Indexing { scanned: usize },
+    /// Done indexing, with the number of items indexed.
+    Done { items: usize },
+    /// Indexing failed with this error, queries keep using the previous items.
+    Failed { error: String },
+}
+
+/// Receives the events plugins send through their [`PluginHandle`], along with the plugin name.
+#[derive(Clone)]
+pub struct PluginEvents {
+    on_event: Arc<EventFn>,
+}
+
+impl std::fmt::Debug for PluginEvents {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginEvents").finish_non_exhaustive()
+    }
+}
+
+impl Default for PluginEvents {
+    fn default() -> Self {
+        Self::new(|_, _| {})
+    }
+}
+
+impl PluginEvents {
+    pub fn new<F>(on_event: F) -> Self
+    where
+        F: Fn(&str, PluginEvent) + 'static + Send + Sync,
+    {
+        Self {
+            on_event: Arc::new(on_event),
+        }
+    }
+
+    /// Creates the handle of `plugin`, passing the events it sends to this receiver.
+    pub fn handle(&self, plugin: &str) -> PluginHandle {
+        PluginHandle {
+            plugin: plugin.into(),
+            events: self.clone(),
+        }
+    }
+}
+
+/// Lets a plugin reach the main window outside of queries,
+/// given to the plugin through [`Plugin::init_handle`](crate::Plugin::init_handle).
+#[derive(Clone, Default)]
+pub struct PluginHandle {
+    plugin: Arc<str>,
+    events: PluginEvents,
+}
+
+impl std::fmt::Debug for PluginHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PluginHandle")
+            .field("plugin", &self.plugin)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PluginHandle {
+    /// Name of the plugin this handle belongs to.
+    pub fn plugin(&self) -> &str {
+        &self.plugin
+    }
+
+    pub fn send(&self, event: PluginEvent) {
+        (self.events.on_event)(&self.plugin, event)
+    }
+
+    /// Reports the progress of indexing in the background, shown next to the search box.
+    pub fn report_indexing(&self, status: IndexingStatus) {
+        self.send(PluginEvent::Indexing(status))
+    }
+
+    /// Queries the plugin again for the query currently shown, replacing its results,
+    /// for example once it learned about new items.
+    ///
+    /// Does nothing if no query is shown or the plugin isn't queried for it.
+    pub fn requery(&self) {
+        self.send(PluginEvent::Requery)
+    }
+
+    /// Replaces the results of the plugin for the query currently shown with `items`.
+    ///
+    /// Does nothing if no query is shown.
+    pub fn push_results(&self, items: Vec<ResultItem>) {
+        self.send(PluginEvent::PushResults(items))
+    }
+}
@@ -1,9 +1,12 @@
 use std::str::FromStr;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use strum::{AsRefStr, EnumString};
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, EnumString)]
+#[derive(
+    Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, EnumString, JsonSchema,
+)]
 pub enum IconType {
     /// [`Icon::data`] is the path to icon.
     Path,
@@ -21,7 +24,7 @@ pub enum IconType {
     Url,
 }
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Debug, Clone, Default, JsonSchema)]
 pub struct Icon {
     pub data: String,
     pub r#type: IconType,
@@ -0,0 +1,194 @@
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock, Weak};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{FuzzyMatcher, IndexingStatus, PluginHandle};
+
+/// Items indexed by a plugin, like the files found in some directories.
+///
+/// Queries use a snapshot of the latest complete index, so the plugin can index again
+/// in the background and swap the new items in once done, see [`Self::replace`].
+///
+/// Indexing again is usually done using [`Self::reindex`], which also reports
+/// its progress to the main window.
+///
+/// The index can be cached on disk, see [`Self::load_cache`], so the items
+/// are available right away on the next startup while they are indexed again.
+pub struct Index<T> {
+    inner: Arc<Inner<T>>,
+}
+
+struct Inner<T> {
+    items: RwLock<Arc<Vec<T>>>,
+    /// File the index is cached in, `None` keeps it in memory only.
+    cache_path: Mutex<Option<PathBuf>>,
+    /// The items of the last query, see [`Index::snapshot`].
+    queried: Mutex<Weak<Vec<T>>>,
+    /// Held while saving, so concurrent saves don't interleave their writes.
+    save_lock: Mutex<()>,
+}
+
+impl<T> Index<T> {
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                items: RwLock::new(Arc::new(Vec::new())),
+                cache_path: Mutex::new(None),
+                queried: Mutex::new(Weak::new()),
+                save_lock: Mutex::new(()),
+            }),
+        }
+    }
+
+    /// The latest complete items.
+    pub fn items(&self) -> Arc<Vec<T>> {
+        self.inner.items.read().unwrap().clone()
+    }
+
+    /// Same as [`Self::items`] but for querying the items with `matcher`,
+    /// forgetting its [cached matches](FuzzyMatcher::candidates)
+    /// if the items changed since the last query.
+    pub fn snapshot(&self, matcher: &mut FuzzyMatcher) -> Arc<Vec<T>> {
+        let items = self.items();
+
+        let mut queried = self.inner.queried.lock().unwrap();
+        if !std::ptr::eq(queried.as_ptr(), Arc::as_ptr(&items)) {
+            matcher.clear_candidates();
+            *queried = Arc::downgrade(&items);
+        }
+
+        items
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.items.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Swaps in newly indexed items, queries that already started keep using the old ones.
+    pub fn replace(&self, items: Vec<T>) {
+        *self.inner.items.write().unwrap() = Arc::new(items);
+    }
+
+    /// Updates the items in place, for small changes like a single file being added.
+    pub fn update(&self, f: impl FnOnce(&mut Vec<T>))
+    where
+        T: Clone,
+    {
+        let mut items = self.inner.items.write().unwrap();
+        // clones the items if a query is still using them
+        f(Arc::make_mut(&mut items));
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> Index<T> {
+    /// Caches the index in the file at `path` from now on, see [`Self::save`],
+    /// and loads the items cached there on a previous run, if any.
+    pub fn load_cache(&self, path: PathBuf) -> anyhow::Result<()> {
+        let cached = if path.exists() {
+            let json = std::fs::read(&path)?;
+            Some(serde_json::from_slice::<Vec<T>>(&json))
+        } else {
+            None
+        };
+
+        *self.inner.cache_path.lock().unwrap() = Some(path);
+
+        if let Some(items) = cached {
+            self.replace(items?);
+        }
+
+        Ok(())
+    }
+
+    /// Saves the items to the cache file set using [`Self::load_cache`], if any.
+    ///
+    /// The items are written to a temporary file first and swapped in once written,
+    /// so a crash while saving doesn't truncate the cache.
+    ///
+    /// This blocks on writing the file, so it is better called from a background thread.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = self.inner.cache_path.lock().unwrap().clone() else {
+            return Ok(());
+        };
+
+        let _saving = self.inner.save_lock.lock().unwrap();
+
+        // serialized while holding the save lock, so the latest items are saved last
+        let json = serde_json::to_vec(&*self.items())?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut temp_path = path.clone().into_os_string();
+        temp_path.push(".tmp");
+
+        std::fs::write(&temp_path, json)?;
+        std::fs::rename(&temp_path, &path).map_err(Into::into)
+    }
+
+    /// Indexes the items again in the background, swapping in and caching
+    /// the items returned by `index` once done, then [requerying](PluginHandle::requery)
+    /// the plugin. Queries keep using the previous items meanwhile, or if `index` fails.
+    ///
+    /// Progress is reported through `handle`, `index` can also report how many items
+    /// it scanned so far with [`IndexingStatus::Indexing`].
+    ///
+    /// Dropping the returned task cancels indexing.
+    pub fn reindex<F>(&self, handle: PluginHandle, index: F) -> smol::Task<()>
+    where
+        F: Future<Output = anyhow::Result<Vec<T>>> + Send + 'static,
+        T: Send + Sync + 'static,
+    {
+        let this = self.clone();
+
+        smol::spawn(async move {
+            handle.report_indexing(IndexingStatus::Indexing { scanned: 0 });
+
+            let items = match index.await {
+                Ok(items) => items,
+                Err(e) => {
+                    let error = e.to_string();
+                    handle.report_indexing(IndexingStatus::Failed { error });
+                    return;
+                }
+            };
+
+            let len = items.len();
+            this.replace(items);
+            handle.report_indexing(IndexingStatus::Done { items: len });
+            handle.requery();
+
+            if let Err(e) = smol::unblock(move || this.save()).await {
+                tracing::error!("[{}] Failed to cache index: {e}", handle.plugin());
+            }
+        })
+    }
+}
+
+impl<T> Default for Index<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for Index<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for Index<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Index").field("len", &self.len()).finish()
+    }
+}
@@ -1,13 +1,21 @@
 pub mod action;
+pub mod cancellation;
 pub mod fuzzy_matcher;
+pub mod handle;
 pub mod icon;
+pub mod index;
+pub mod payload;
 pub mod plugin;
 pub mod query_output;
 pub mod result_item;
 
 pub use self::action::*;
+pub use self::cancellation::*;
 pub use self::fuzzy_matcher::*;
+pub use self::handle::*;
 pub use self::icon::*;
+pub use self::index::*;
+pub use self::payload::*;
 pub use self::plugin::*;
 pub use self::query_output::*;
 pub use self::result_item::*;
@@ -0,0 +1,83 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Action;
+
+/// What's needed to run the actions of a [`ResultItem`](crate::ResultItem)
+/// that points to a file, a directory or an app.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PathPayload {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub is_dir: bool,
+    /// Arguments passed to `path` when it is executed.
+    #[serde(default)]
+    pub args: String,
+    /// The directory [`Action::OPEN_LOCATION`] opens,
+    /// when not set, `path` is revealed in its parent directory instead.
+    #[serde(default)]
+    pub location: Option<PathBuf>,
+}
+
+impl PathPayload {
+    pub fn new(path: impl Into<PathBuf>, is_dir: bool) -> Self {
+        Self {
+            path: path.into(),
+            is_dir,
+            ..Default::default()
+        }
+    }
+
+    /// The dispatched actions this payload can [run](Self::run),
+    /// directories can't be run elevated.
+    pub fn actions(&self) -> Vec<Action> {
+        if self.is_dir {
+            vec![
+                Action::dispatch(Action::PRIMARY),
+                Action::dispatch(Action::OPEN_LOCATION),
+            ]
+        } else {
+            vec![
+                Action::dispatch(Action::PRIMARY),
+                Action::dispatch(Action::RUN_ELEVATED),
+                Action::dispatch(Action::OPEN_LOCATION),
+            ]
+        }
+    }
+
+    /// Serializes this payload for [`ResultItem::payload`](crate::ResultItem::payload).
+    ///
+    /// Fails for paths that aren't valid unicode, these are logged
+    /// and their items should be skipped since their actions couldn't run.
+    pub fn to_value(&self) -> Option<serde_json::Value> {
+        serde_json::to_value(self)
+            .inspect_err(|e| {
+                tracing::warn!(
+                    "Failed to serialize payload of {}: {e}",
+                    self.path.display()
+                )
+            })
+            .ok()
+    }
+
+    pub fn run(&self, action: &str) -> anyhow::Result<()> {
+        match action {
+            Action::PRIMARY if self.is_dir => kal_utils::open_dir(&self.path),
+            Action::PRIMARY | Action::RUN_ELEVATED => {
+                let elevated = action == Action::RUN_ELEVATED;
+                if self.args.is_empty() {
+                    kal_utils::execute(&self.path, elevated)
+                } else {
+                    kal_utils::execute_with_args(&self.path, &self.args, elevated, false)
+                }
+            }
+            Action::OPEN_LOCATION => match &self.location {
+                Some(location) => kal_utils::open_dir(location),
+                None => kal_utils::reveal_item_in_dir(&self.path),
+            },
+            _ => anyhow::bail!("Unknown action `{action}`"),
+        }
+    }
+}
@@ -1,6 +1,11 @@
-use kal_config::{Config, PluginConfig};
+use std::path::Path;
 
-use crate::{FuzzyMatcher, PluginQueryOutput};
+use kal_config::{Config, ConfigSchema, PluginConfig};
+
+use crate::{
+    ActionFuture, ActionProgress, CancellationToken, Candidate, FuzzyMatcher, PluginHandle,
+    PluginQueryOutput, ResultItem,
+};
 
 #[async_trait::async_trait]
 pub trait Plugin: Send + Sync {
@@ -13,7 +18,7 @@ pub trait Plugin: Send + Sync {
     ///
     /// usually used to identify the origin of a [`ResultItem`]
     /// and the plugin to exceute it.
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     /// Default plugin config
     fn default_plugin_config(&self) -> PluginConfig {
@@ -21,32 +26,128 @@ pub trait Plugin: Send + Sync {
             enabled: Some(true),
             include_in_global_results: Some(true),
             direct_activation_command: None,
+            query_timeout: None,
             inner: None,
         }
     }
 
-    /// Reloads the cache and configuration of the plugin
+    /// Keywords that invoke this plugin directly, like its `directActivationCommand`,
+    /// for plugins that can be invoked in more than one way, like script filters.
+    ///
+    /// A keyword must be followed by a space or end the query, and unlike the
+    /// `directActivationCommand`, it is kept in the query passed to [`Self::query_direct`],
+    /// so the plugin knows which one was typed. Read again after each [`Self::reload`].
+    fn direct_activation_keywords(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Adds the options of this plugin's own config to the config JSON schema,
+    /// usually with [`ConfigSchema::plugin`] and the type its options are deserialized into.
+    #[allow(unused_variables)]
+    fn config_schema(schema: &mut ConfigSchema)
+    where
+        Self: Sized,
+    {
+    }
+
+    /// Called once at startup, before the first [`Self::reload`], with a directory
+    /// the plugin can cache data in across runs, like its [`Index`](crate::Index).
+    #[allow(unused_variables)]
+    fn init_cache(&mut self, cache_dir: &Path) {}
+
+    /// Called once when the plugin is loaded, before the first [`Self::reload`],
+    /// with a handle to reach the main window outside of queries.
+    #[allow(unused_variables)]
+    fn init_handle(&mut self, handle: PluginHandle) {}
+
+    /// Reloads the cache and configuration of the plugin.
+    ///
+    /// Reloading is awaited before kal is usable again, so slow work
+    /// like indexing should run in the background, see [`Index::reindex`](crate::Index::reindex).
     #[allow(unused_variables)]
     async fn reload(&mut self, config: &Config) -> anyhow::Result<()> {
         Ok(())
     }
 
+    /// Called when the main window is shown, to refresh state that changes
+    /// while kal is hidden, like the clipboard contents or running processes.
+    ///
+    /// Runs concurrently with queries, so the user may start typing before it finishes.
+    async fn on_show(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called when the main window is hidden.
+    async fn on_hide(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Called once when kal is exiting, to flush state to disk.
+    ///
+    /// kal only waits for a short while before exiting, so this should be quick.
+    async fn on_shutdown(&self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
     /// Query the plugin for [`ResultItem`]s.
+    ///
+    /// Plugins are queried concurrently and the returned future is dropped
+    /// if it doesn't finish before the plugin's query timeout, so long running
+    /// work should yield regularly or be moved off the async executor.
+    ///
+    /// The future is also dropped once `cancellation` is cancelled because the user
+    /// typed a newer query, work that isn't tied to the future, like a blocking
+    /// task on another thread, should check [`CancellationToken::is_cancelled`]
+    /// and bail out early.
     #[allow(unused_variables)]
     async fn query(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut FuzzyMatcher,
+        cancellation: &CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         Ok(PluginQueryOutput::None)
     }
 
     /// Query the plugin for [`ResultItem`]s when directly invoked.
     async fn query_direct(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut crate::fuzzy_matcher::FuzzyMatcher,
+        cancellation: &CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
-        self.query(query, matcher).await
+        self.query(query, matcher, cancellation).await
+    }
+
+    /// Builds the [`ResultItem`] of a `candidate` this plugin returned for `query`
+    /// in [`PluginQueryOutput::Candidates`], only called for the candidates
+    /// that rank among the results shown.
+    ///
+    /// Returns `None` if the matched item is gone.
+    #[allow(unused_variables)]
+    fn materialize(
+        &self,
+        candidate: &Candidate,
+        query: &str,
+        matcher: &mut FuzzyMatcher,
+    ) -> Option<ResultItem> {
+        None
+    }
+
+    /// Runs the [dispatched](crate::Action::dispatch) action with `action` id
+    /// of an item produced by this plugin, `payload` is the item's
+    /// [`ResultItem::payload`](crate::ResultItem::payload).
+    ///
+    /// The returned future shouldn't borrow the plugin, so the plugin
+    /// can be queried and reloaded while the action is running.
+    #[allow(unused_variables)]
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        progress: ActionProgress,
+    ) -> ActionFuture {
+        let error = anyhow::anyhow!("`{}` doesn't support running `{action}`", self.name());
+        crate::ready_outcome::<crate::ActionOutcome>(Err(error))
     }
 }
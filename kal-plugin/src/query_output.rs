@@ -1,25 +1,82 @@
+use std::pin::Pin;
+use std::sync::Arc;
+
+use futures_lite::{Stream, StreamExt};
+
 use crate::ResultItem;
 
+/// A stream of [`ResultItem`] batches.
+pub type ResultStream = Pin<Box<dyn Stream<Item = Vec<ResultItem>> + Send>>;
+
+/// A match found by a plugin that is only turned into a [`ResultItem`],
+/// using [`Plugin::materialize`](crate::Plugin::materialize), if it ranks among the results shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candidate {
+    /// Identifies the matched item to its plugin, like its index in the plugin's items.
+    pub key: usize,
+    /// Id of the [`ResultItem`] this candidate turns into, used to rank it by frecency.
+    pub id: Arc<str>,
+    pub score: u16,
+}
+
 /// Possible output from querying a plugin.
 pub enum PluginQueryOutput {
     None,
-    One(ResultItem),
+    One(Box<ResultItem>),
     Multiple(Vec<ResultItem>),
+    /// Results that are produced incrementally, each batch
+    /// is shown as soon as it arrives and merged with the previous ones.
+    Stream(ResultStream),
+    /// Matches that are only turned into [`ResultItem`]s if they rank high enough to be shown,
+    /// so plugins matching a lot of items don't build a [`ResultItem`] for each of them.
+    Candidates(Vec<Candidate>),
 }
 
 impl PluginQueryOutput {
-    pub fn extend_into(self, results: &mut Vec<ResultItem>) {
+    /// Creates a [`PluginQueryOutput::Stream`] from a stream of result batches.
+    pub fn stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = Vec<ResultItem>> + Send + 'static,
+    {
+        PluginQueryOutput::Stream(Box::pin(stream))
+    }
+
+    /// Extends `results` with this output, waiting for
+    /// the whole stream if it is a [`PluginQueryOutput::Stream`].
+    ///
+    /// [`PluginQueryOutput::Candidates`] are skipped, only their plugin can build their results.
+    pub async fn extend_into(self, results: &mut Vec<ResultItem>) {
         match self {
             PluginQueryOutput::None => {}
-            PluginQueryOutput::One(one) => results.push(one),
+            PluginQueryOutput::One(one) => results.push(*one),
             PluginQueryOutput::Multiple(multiple) => results.extend(multiple),
+            PluginQueryOutput::Stream(mut stream) => {
+                while let Some(batch) = stream.next().await {
+                    results.extend(batch);
+                }
+            }
+            PluginQueryOutput::Candidates(_) => {}
+        }
+    }
+
+    /// Converts this output into a stream of result batches.
+    ///
+    /// [`PluginQueryOutput::Candidates`] are skipped, only their plugin can build their results.
+    pub fn into_stream(self) -> ResultStream {
+        match self {
+            PluginQueryOutput::None | PluginQueryOutput::Candidates(_) => {
+                Box::pin(futures_lite::stream::empty())
+            }
+            PluginQueryOutput::One(one) => Box::pin(futures_lite::stream::once(vec![*one])),
+            PluginQueryOutput::Multiple(multiple) => Box::pin(futures_lite::stream::once(multiple)),
+            PluginQueryOutput::Stream(stream) => stream,
         }
     }
 }
 
 impl From<ResultItem> for PluginQueryOutput {
     fn from(value: ResultItem) -> Self {
-        PluginQueryOutput::One(value)
+        PluginQueryOutput::One(Box::new(value))
     }
 }
 
@@ -29,10 +86,16 @@ impl From<Vec<ResultItem>> for PluginQueryOutput {
     }
 }
 
+impl From<Vec<Candidate>> for PluginQueryOutput {
+    fn from(value: Vec<Candidate>) -> Self {
+        PluginQueryOutput::Candidates(value)
+    }
+}
+
 impl From<Option<ResultItem>> for PluginQueryOutput {
     fn from(value: Option<ResultItem>) -> Self {
         match value {
-            Some(value) => PluginQueryOutput::One(value),
+            Some(value) => value.into(),
             None => PluginQueryOutput::None,
         }
     }
@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 use serde::Serialize;
 
 use crate::{Action, Icon};
@@ -11,8 +13,66 @@ pub struct ResultItem {
     pub tooltip: Option<String>,
     pub actions: Vec<Action>,
     pub score: u16,
+    /// Which characters of the texts matched the query, shown highlighted.
+    pub highlights: Highlights,
+    /// What the plugin needs to run this item's [dispatched](Action::dispatch) actions,
+    /// like a path, passed back to [`Plugin::run_action`](crate::Plugin::run_action).
+    #[serde(skip)]
+    pub payload: serde_json::Value,
+}
+
+impl ResultItem {
+    /// Sorts the secondary actions by [`Action::order`], the primary action stays first.
+    pub fn sort_actions(&mut self) {
+        if let Some((_primary, secondary)) = self.actions.split_first_mut() {
+            secondary.sort_by_key(|action| action.order);
+        }
+    }
+}
+
+/// Ranges of characters to highlight in the texts of a [`ResultItem`],
+/// usually from [`FuzzyMatch::ranges`](crate::FuzzyMatch::ranges).
+#[derive(Serialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct Highlights {
+    pub primary_text: Vec<Range<usize>>,
+    pub secondary_text: Vec<Range<usize>>,
+}
+
+impl Highlights {
+    /// Highlights these ranges of the primary text.
+    pub fn primary(ranges: Vec<Range<usize>>) -> Self {
+        Self {
+            primary_text: ranges,
+            ..Default::default()
+        }
+    }
+
+    /// Highlights these ranges of the secondary text.
+    pub fn secondary(ranges: Vec<Range<usize>>) -> Self {
+        Self {
+            secondary_text: ranges,
+            ..Default::default()
+        }
+    }
 }
 
 pub trait IntoResultItem {
     fn fuzzy_match(&self, query: &str, matcher: &mut crate::FuzzyMatcher) -> Option<ResultItem>;
+
+    /// Same as [`Self::fuzzy_match`] but only returns the score, implement it
+    /// to match without building a [`ResultItem`], see [`crate::Candidate`].
+    fn fuzzy_score(&self, query: &str, matcher: &mut crate::FuzzyMatcher) -> Option<u16> {
+        self.fuzzy_match(query, matcher).map(|item| item.score)
+    }
+
+    /// Extra words this item can be found by besides its texts,
+    /// like aliases defined by the user, see [`Self::fuzzy_match_keywords`].
+    fn keywords(&self) -> &[String] {
+        &[]
+    }
+
+    /// Matches `query` against [`Self::keywords`], returning the best score.
+    fn fuzzy_match_keywords(&self, query: &str, matcher: &mut crate::FuzzyMatcher) -> Option<u16> {
+        matcher.fuzzy_match_any(self.keywords(), query)
+    }
 }
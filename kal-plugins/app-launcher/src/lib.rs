@@ -1,11 +1,15 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::sync::{Arc, Mutex};
 
 use kal_config::Config;
-use kal_plugin::{IntoResultItem, PluginQueryOutput, ResultItem};
-use kal_utils::IteratorExt;
+use kal_plugin::{
+    ActionFuture, ActionProgress, Candidate, Index, IndexingStatus, IntoResultItem, PathPayload,
+    PluginHandle, PluginQueryOutput, ResultItem,
+};
 use notify::RecommendedWatcher;
 use notify_debouncer_mini::Debouncer;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use windows::ApplicationModel::PackageCatalog;
 
@@ -13,12 +17,19 @@ use windows::ApplicationModel::PackageCatalog;
 mod packaged_app;
 mod program;
 
+/// Extra names to find apps by, keyed by the app name.
+type Aliases = HashMap<String, Vec<String>>;
+
 #[derive(Debug)]
 pub struct Plugin {
     paths: Vec<String>,
     extensions: Vec<String>,
     include_packaged_apps: bool,
-    apps: Arc<Mutex<Vec<App>>>,
+    aliases: Arc<Mutex<Aliases>>,
+    apps: Index<App>,
+    handle: PluginHandle,
+    /// Finding apps in the background, dropping it cancels finding apps.
+    finding_apps: Option<smol::Task<()>>,
     programs_watcher: Option<Debouncer<RecommendedWatcher>>,
     #[cfg(windows)]
     package_catalog: Option<PackageCatalog>,
@@ -29,40 +40,69 @@ unsafe impl Send for Plugin {}
 #[cfg(windows)]
 unsafe impl Sync for Plugin {}
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
 #[serde(deny_unknown_fields)]
+#[schemars(rename = "AppLauncherConfig")]
 struct PluginConfig {
+    /// Directories to search for apps, may contain `%VAR%` environment variables.
     #[serde(default = "default_paths")]
     paths: Vec<String>,
+    /// Extensions of the files considered apps.
     #[serde(default = "default_extensions")]
     extensions: Vec<String>,
+    /// Whether to include apps installed from the Microsoft Store.
     #[serde(default = "default_include_packaged_apps")]
     include_packaged_apps: bool,
+    /// Extra names to match apps by, keyed by the app name.
+    #[serde(default)]
+    aliases: Aliases,
 }
 
 impl Plugin {
     const NAME: &'static str = "AppLauncher";
+    const QUERY_CHUNK_SIZE: usize = 256;
 
     fn update_config(&mut self, config: &Config) {
         let config = config.plugin_config::<PluginConfig>(Self::NAME);
         self.paths = config.paths;
         self.extensions = config.extensions;
         self.include_packaged_apps = config.include_packaged_apps;
+        *self.aliases.lock().unwrap() = config.aliases;
     }
 
-    async fn find_apps(&mut self) {
-        *self.apps.lock().unwrap() =
-            program::find_all_in_paths(&self.paths, &self.extensions).await;
+    /// Finds the apps again in the background and swaps them in once done.
+    fn find_apps(&mut self) {
+        let paths = self.paths.clone();
+        let extensions = self.extensions.clone();
+        let include_packaged_apps = self.include_packaged_apps;
+        let aliases = self.aliases.clone();
+        let handle = self.handle.clone();
 
-        #[cfg(windows)]
-        if self.include_packaged_apps {
-            if let Ok(packaged_apps) = packaged_app::find_all() {
-                self.apps
-                    .lock()
-                    .unwrap()
-                    .extend(packaged_apps.map(App::Packaged));
+        let finding_apps = self.apps.reindex(self.handle.clone(), async move {
+            let mut apps = program::find_all_in_paths(&paths, &extensions).await;
+
+            let scanned = apps.len();
+            handle.report_indexing(IndexingStatus::Indexing { scanned });
+
+            #[cfg(windows)]
+            if include_packaged_apps {
+                if let Ok(packaged_apps) = packaged_app::find_all() {
+                    apps.extend(packaged_apps.map(App::Packaged));
+                }
             }
-        }
+
+            {
+                let aliases = aliases.lock().unwrap();
+                for app in apps.iter_mut() {
+                    app.add_aliases(&aliases);
+                }
+            }
+
+            Ok(apps)
+        });
+
+        // cancels finding apps in outdated paths
+        self.finding_apps.replace(finding_apps);
     }
 }
 
@@ -75,28 +115,47 @@ impl kal_plugin::Plugin for Plugin {
             paths: config.paths,
             extensions: config.extensions,
             include_packaged_apps: config.include_packaged_apps,
-            apps: Default::default(),
+            aliases: Arc::new(Mutex::new(config.aliases)),
+            apps: Index::new(),
+            handle: PluginHandle::default(),
+            finding_apps: None,
             programs_watcher: None,
             package_catalog: None,
         }
     }
 
+    fn config_schema(schema: &mut kal_config::ConfigSchema) {
+        schema.plugin::<PluginConfig>(Self::NAME);
+    }
+
     fn name(&self) -> &'static str {
         Self::NAME
     }
 
+    fn init_cache(&mut self, cache_dir: &Path) {
+        let path = cache_dir.join(format!("{}.json", Self::NAME));
+        if let Err(e) = self.apps.load_cache(path) {
+            tracing::error!("[AppLauncher] Failed to load cached apps: {e}");
+        }
+    }
+
+    fn init_handle(&mut self, handle: PluginHandle) {
+        self.handle = handle;
+    }
+
     fn default_plugin_config(&self) -> kal_config::PluginConfig {
         kal_config::PluginConfig {
             enabled: Some(true),
             include_in_global_results: Some(true),
             direct_activation_command: Some(".".into()),
+            query_timeout: None,
             inner: toml::Table::try_from(PluginConfig::default()).ok(),
         }
     }
 
     async fn reload(&mut self, config: &Config) -> anyhow::Result<()> {
         self.update_config(config);
-        self.find_apps().await;
+        self.find_apps();
         self.watch_programs()?;
 
         #[cfg(windows)]
@@ -108,26 +167,70 @@ impl kal_plugin::Plugin for Plugin {
     }
 
     async fn query(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         if query.is_empty() {
             return Ok(PluginQueryOutput::None);
         }
 
-        Ok(self
-            .apps
-            .lock()
-            .unwrap()
-            .iter()
-            .filter_map(|app| app.fuzzy_match(query, matcher))
-            .collect_non_empty::<Vec<_>>()
-            .into())
+        let apps = self.apps.snapshot(matcher);
+
+        // results are only built for the apps that are shown
+        let mut results = Vec::new();
+
+        for (chunk, apps) in apps.chunks(Self::QUERY_CHUNK_SIZE).enumerate() {
+            if cancellation.is_cancelled() {
+                return Ok(PluginQueryOutput::None);
+            }
+
+            for (idx, app) in apps.iter().enumerate() {
+                if let Some(score) = app.fuzzy_score(query, matcher) {
+                    let key = chunk * Self::QUERY_CHUNK_SIZE + idx;
+                    let id = app.id().clone();
+                    results.push(Candidate { key, id, score });
+                }
+            }
+
+            // plugins are queried on the same task,
+            // yield so the others, the query timeout or cancellation can kick in
+            smol::future::yield_now().await;
+        }
+
+        Ok(results.into())
+    }
+
+    fn materialize(
+        &self,
+        candidate: &Candidate,
+        query: &str,
+        matcher: &mut kal_plugin::FuzzyMatcher,
+    ) -> Option<ResultItem> {
+        let apps = self.apps.items();
+
+        // apps may have been added or removed since the query
+        let app = apps.get(candidate.key)?;
+        if *app.id() != candidate.id {
+            return None;
+        }
+
+        app.fuzzy_match(query, matcher)
+    }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let run = || serde_json::from_value::<PathPayload>(payload)?.run(action);
+        kal_plugin::ready_outcome(run())
     }
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 enum App {
     Program(program::Program),
     #[cfg(windows)]
@@ -142,12 +245,35 @@ impl App {
         }
     }
 
+    pub fn id(&self) -> &Arc<str> {
+        match self {
+            App::Program(program) => &program.id,
+            #[cfg(windows)]
+            App::Packaged(packaged_app) => &packaged_app.id,
+        }
+    }
+
     pub fn path(&self) -> Option<&Path> {
         match self {
             App::Program(program) => Some(&program.path),
             App::Packaged(_) => None,
         }
     }
+
+    /// Adds the aliases the user defined for this app to its keywords.
+    fn add_aliases(&mut self, aliases: &Aliases) {
+        let Some(aliases) = aliases.get(self.name()) else {
+            return;
+        };
+
+        let keywords = match self {
+            App::Program(program) => &mut program.keywords,
+            #[cfg(windows)]
+            App::Packaged(packaged_app) => &mut packaged_app.keywords,
+        };
+
+        keywords.extend(aliases.iter().cloned());
+    }
 }
 
 impl IntoResultItem for App {
@@ -162,6 +288,14 @@ impl IntoResultItem for App {
             App::Packaged(packaged_app) => packaged_app.fuzzy_match(query, matcher),
         }
     }
+
+    fn fuzzy_score(&self, query: &str, matcher: &mut kal_plugin::FuzzyMatcher) -> Option<u16> {
+        match self {
+            App::Program(program) => program.fuzzy_score(query, matcher),
+            #[cfg(windows)]
+            App::Packaged(packaged_app) => packaged_app.fuzzy_score(query, matcher),
+        }
+    }
 }
 
 impl Default for PluginConfig {
@@ -170,6 +304,7 @@ impl Default for PluginConfig {
             paths: default_paths(),
             extensions: default_extensions(),
             include_packaged_apps: default_include_packaged_apps(),
+            aliases: Aliases::new(),
         }
     }
 }
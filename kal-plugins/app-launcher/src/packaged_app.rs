@@ -1,8 +1,10 @@
 use std::ffi::OsString;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-use kal_plugin::{Action, BuiltinIcon, Icon, IntoResultItem, ResultItem};
+use kal_plugin::{BuiltinIcon, Highlights, Icon, IntoResultItem, PathPayload, ResultItem};
 use kal_utils::StringExt;
+use serde::{Deserialize, Serialize};
 use windows::core::{w, HSTRING, PCWSTR};
 use windows::ApplicationModel::{
     Package, PackageCatalog, PackageInstallingEventArgs, PackageUninstallingEventArgs,
@@ -19,7 +21,7 @@ use windows::Win32::UI::Shell::{SHCreateStreamOnFileEx, SHLoadIndirectString};
 
 const MS_RESOURCE: &str = "ms-resource:";
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 struct PackageId {
     name: String,
     full_name: String,
@@ -38,52 +40,45 @@ impl PackageId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct PackagedApp {
     pub name: String,
     pub icon: Option<OsString>,
     pub appid: String,
-    pub id: String,
+    pub id: Arc<str>,
     pub location: PathBuf,
+    /// Other names to find this app by, like aliases defined by the user.
+    pub keywords: Vec<String>,
     package_id: PackageId,
 }
 
 impl PackagedApp {
-    fn item(&self, args: &str, score: u16) -> ResultItem {
+    fn item(&self, args: &str, score: u16, highlights: Highlights) -> Option<ResultItem> {
         let icon = self
             .icon
             .as_ref()
             .map(|i| Icon::path(i.to_string_lossy()))
             .unwrap_or_else(|| BuiltinIcon::BlankFile.into());
 
-        let appid = self.appid.clone();
-        let args_ = args.to_string();
-        let open = Action::primary(move |_| {
-            let path = format!("shell:AppsFolder\\{}", appid);
-            kal_utils::execute_with_args(path, &args_, false, false)
-        });
-
-        let appid = self.appid.clone();
-        let args_ = args.to_string();
-        let open_elevated = Action::open_elevated(move |_| {
-            let path = format!("shell:AppsFolder\\{}", appid);
-            kal_utils::execute_with_args(path, &args_, true, false)
-        });
-
-        let location = self.location.clone();
-        let open_location = Action::open_location(move |_| kal_utils::open_dir(&location));
+        let payload = PathPayload {
+            args: args.to_string(),
+            location: Some(self.location.clone()),
+            ..PathPayload::new(format!("shell:AppsFolder\\{}", self.appid), false)
+        };
 
         let tooltip = format!("{}\n{}", self.name, self.location.display());
 
-        ResultItem {
-            id: self.id.clone(),
+        Some(ResultItem {
+            id: self.id.to_string(),
             icon,
             primary_text: self.name.clone(),
             secondary_text: "Packaged Application".into(),
             tooltip: Some(tooltip),
-            actions: vec![open, open_elevated, open_location],
+            actions: payload.actions(),
             score,
-        }
+            highlights,
+            payload: payload.to_value()?,
+        })
     }
 }
 
@@ -95,9 +90,26 @@ impl IntoResultItem for PackagedApp {
     ) -> Option<ResultItem> {
         let (query, args) = query.split_args().unwrap_or((query, ""));
 
+        matcher
+            .fuzzy_indices(&self.name, query)
+            .map(|m| (m.score, Highlights::primary(m.ranges)))
+            .or_else(|| {
+                self.fuzzy_match_keywords(query, matcher)
+                    .map(|score| (score, Highlights::default()))
+            })
+            .and_then(|(score, highlights)| self.item(args, score, highlights))
+    }
+
+    fn fuzzy_score(&self, query: &str, matcher: &mut kal_plugin::FuzzyMatcher) -> Option<u16> {
+        let (query, _) = query.split_args().unwrap_or((query, ""));
+
         matcher
             .fuzzy_match(&self.name, query)
-            .map(|score| self.item(args, score))
+            .or_else(|| self.fuzzy_match_keywords(query, matcher))
+    }
+
+    fn keywords(&self) -> &[String] {
+        &self.keywords
     }
 }
 
@@ -188,11 +200,12 @@ fn app_from_manifest(
     let name = display_name.to_string();
 
     Ok(Some(PackagedApp {
-        id: format!("{}:{}", super::Plugin::NAME, name),
+        id: format!("{}:{}", super::Plugin::NAME, name).into(),
         name,
         icon: logo.ok(),
         appid: appid.to_string(),
         location: PathBuf::from(package.InstalledPath()?.to_os_string()),
+        keywords: Vec::new(),
         package_id: PackageId::from_package(package)?,
     }))
 }
@@ -235,6 +248,7 @@ impl super::Plugin {
         let catalog = PackageCatalog::OpenForCurrentUser()?;
 
         let apps = self.apps.clone();
+        let aliases = self.aliases.clone();
         catalog.PackageInstalling(&TypedEventHandler::new(move |_, args| {
             let Some(args): &Option<PackageInstallingEventArgs> = args else {
                 return Ok(());
@@ -242,7 +256,7 @@ impl super::Plugin {
 
             if args.IsComplete() == Ok(true) {
                 let package = args.Package()?;
-                add_package(&mut apps.lock().unwrap(), package);
+                apps.update(|apps| add_package(apps, &aliases.lock().unwrap(), package));
             }
 
             Ok(())
@@ -256,13 +270,14 @@ impl super::Plugin {
 
             if args.Progress() == Ok(0.) {
                 let package = args.Package()?;
-                remove_package(&mut apps.lock().unwrap(), package);
+                apps.update(|apps| remove_package(apps, package));
             }
 
             Ok(())
         }))?;
 
         let apps = self.apps.clone();
+        let aliases = self.aliases.clone();
         catalog.PackageUpdating(&TypedEventHandler::new(move |_, args| {
             let Some(args): &Option<PackageUpdatingEventArgs> = args else {
                 return Ok(());
@@ -270,12 +285,12 @@ impl super::Plugin {
 
             if args.Progress() == Ok(0.) {
                 let package = args.SourcePackage()?;
-                remove_package(&mut apps.lock().unwrap(), package);
+                apps.update(|apps| remove_package(apps, package));
             }
 
             if args.IsComplete() == Ok(true) {
                 let package = args.TargetPackage()?;
-                add_package(&mut apps.lock().unwrap(), package);
+                apps.update(|apps| add_package(apps, &aliases.lock().unwrap(), package));
             }
 
             Ok(())
@@ -287,7 +302,7 @@ impl super::Plugin {
     }
 }
 
-fn add_package(apps: &mut Vec<super::App>, package: Package) {
+fn add_package(apps: &mut Vec<super::App>, aliases: &super::Aliases, package: Package) {
     if package
         .InstalledPath()
         .map(|p| p.is_empty())
@@ -309,7 +324,11 @@ fn add_package(apps: &mut Vec<super::App>, package: Package) {
         return;
     };
 
-    apps.extend(new_apps.into_iter().map(super::App::Packaged));
+    apps.extend(new_apps.into_iter().map(|app| {
+        let mut app = super::App::Packaged(app);
+        app.add_aliases(aliases);
+        app
+    }));
 }
 
 fn remove_package(apps: &mut Vec<super::App>, package: Package) {
@@ -1,33 +1,46 @@
 use std::ffi::{OsStr, OsString};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Duration;
 
-use kal_plugin::{Action, Icon, IntoResultItem, ResultItem};
+use kal_plugin::{Highlights, Icon, IntoResultItem, PathPayload, ResultItem};
 use kal_utils::{PathExt, StringExt};
+use serde::{Deserialize, Serialize};
 use smol::prelude::*;
 
 use super::App;
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Program {
     pub name: OsString,
     pub path: PathBuf,
-    pub id: String,
+    pub id: Arc<str>,
     pub description: String,
+    /// Other names to find this program by, like the name of a shortcut's target.
+    pub keywords: Vec<String>,
 }
 
 impl Program {
     pub fn new(path: PathBuf) -> Self {
         let name = path.file_stem().unwrap_or_default().to_os_string();
         let filename = path.file_name().unwrap_or_default().to_os_string();
-        let id = format!("{}:{}", super::Plugin::NAME, filename.to_string_lossy());
+        let id = format!("{}:{}", super::Plugin::NAME, filename.to_string_lossy()).into();
 
         let mut description = String::from("Application");
+        let mut keywords = Vec::new();
 
         #[cfg(windows)]
         if path.extension() == Some(OsStr::new("lnk")) {
             if let Ok(target) = kal_utils::resolve_shortcut_target(&path) {
                 description = get_app_type(&target).description().into();
+
+                // shortcuts are often named differently than what they launch,
+                // like `Visual Studio Code` launching `Code.exe`
+                if let Some(target_name) = target.file_stem() {
+                    if target_name != name.as_os_str() {
+                        keywords.push(target_name.to_string_lossy().into_owned());
+                    }
+                }
             }
         }
 
@@ -36,35 +49,29 @@ impl Program {
             path,
             id,
             description,
+            keywords,
         }
     }
 
-    fn item(&self, args: &str, score: u16) -> ResultItem {
-        let path = self.path.clone();
-        let args_ = args.to_string();
-        let open =
-            Action::primary(move |_| kal_utils::execute_with_args(&path, &args_, false, false));
-
-        let path = self.path.clone();
-        let args_ = args.to_string();
-        let open_elevated = Action::open_elevated(move |_| {
-            kal_utils::execute_with_args(&path, &args_, true, false)
-        });
-
-        let path = self.path.clone();
-        let open_location = Action::open_location(move |_| kal_utils::reveal_item_in_dir(&path));
+    fn item(&self, args: &str, score: u16, highlights: Highlights) -> Option<ResultItem> {
+        let payload = PathPayload {
+            args: args.to_string(),
+            ..PathPayload::new(&self.path, false)
+        };
 
         let tooltip = format!("{}\n{}", self.name.to_string_lossy(), self.path.display());
 
-        ResultItem {
-            id: self.id.as_str().into(),
+        Some(ResultItem {
+            id: self.id.to_string(),
             icon: Icon::extract_path(self.path.to_string_lossy()),
             primary_text: self.name.to_string_lossy().into_owned(),
             secondary_text: self.description.clone(),
             tooltip: Some(tooltip),
-            actions: vec![open, open_elevated, open_location],
+            actions: payload.actions(),
             score,
-        }
+            highlights,
+            payload: payload.to_value()?,
+        })
     }
 }
 
@@ -76,10 +83,33 @@ impl IntoResultItem for Program {
     ) -> Option<ResultItem> {
         let (query, args) = query.split_args().unwrap_or((query, ""));
 
+        matcher
+            .fuzzy_indices(&self.name.to_string_lossy(), query)
+            .map(|m| (m.score, Highlights::primary(m.ranges)))
+            .or_else(|| {
+                self.fuzzy_match_keywords(query, matcher)
+                    .map(|score| (score, Highlights::default()))
+            })
+            .or_else(|| {
+                // the path isn't shown so there is nothing to highlight
+                matcher
+                    .fuzzy_match(&self.path.to_string_lossy(), query)
+                    .map(|score| (score, Highlights::default()))
+            })
+            .and_then(|(score, highlights)| self.item(args, score, highlights))
+    }
+
+    fn fuzzy_score(&self, query: &str, matcher: &mut kal_plugin::FuzzyMatcher) -> Option<u16> {
+        let (query, _) = query.split_args().unwrap_or((query, ""));
+
         matcher
             .fuzzy_match(&self.name.to_string_lossy(), query)
+            .or_else(|| self.fuzzy_match_keywords(query, matcher))
             .or_else(|| matcher.fuzzy_match(&self.path.to_string_lossy(), query))
-            .map(|score| self.item(args, score))
+    }
+
+    fn keywords(&self) -> &[String] {
+        &self.keywords
     }
 }
 
@@ -144,6 +174,7 @@ impl super::Plugin {
         use notify_debouncer_mini::DebounceEventResult;
 
         let apps = self.apps.clone();
+        let aliases = self.aliases.clone();
         let extensions = self.extensions.clone();
 
         let dur = Duration::from_secs(1);
@@ -159,20 +190,22 @@ impl super::Plugin {
 
                 let flt = |ext| path.extension() == Some(OsStr::new(ext));
                 if extensions.iter().any(flt) {
-                    let mut apps = apps.lock().unwrap();
-                    if let Some(pos) = apps.iter().position(|app| app.path() == Some(&path)) {
-                        tracing::trace!("[AppLauncher] removing {}", apps[pos].name());
+                    apps.update(|apps| {
+                        if let Some(pos) = apps.iter().position(|app| app.path() == Some(&path)) {
+                            tracing::trace!("[AppLauncher] removing {}", apps[pos].name());
 
-                        apps.remove(pos);
-                    }
+                            apps.remove(pos);
+                        }
 
-                    if path.exists() {
-                        let program = Program::new(path);
+                        if path.exists() {
+                            let mut app = App::Program(Program::new(path));
+                            app.add_aliases(&aliases.lock().unwrap());
 
-                        tracing::trace!("[AppLauncher] Adding {}", program.name.to_string_lossy());
+                            tracing::trace!("[AppLauncher] Adding {}", app.name());
 
-                        apps.push(App::Program(program));
-                    }
+                            apps.push(app);
+                        }
+                    });
                 }
             }
         })?;
@@ -21,6 +21,8 @@ impl Plugin {
                 clipboard.set_text(&item.primary_text).map_err(Into::into)
             })],
             score: 200,
+            highlights: Default::default(),
+            payload: Default::default(),
         }
     }
 }
@@ -40,14 +42,16 @@ impl kal_plugin::Plugin for Plugin {
             enabled: Some(true),
             include_in_global_results: Some(true),
             direct_activation_command: Some("=".into()),
+            query_timeout: None,
             inner: None,
         }
     }
 
     async fn query(
-        &mut self,
+        &self,
         query: &str,
         _matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         let mut ctx = sci_calc::context::Context::new();
 
@@ -56,17 +60,18 @@ impl kal_plugin::Plugin for Plugin {
         };
 
         let item = self.item(result.to_string());
-        Ok(PluginQueryOutput::One(item))
+        Ok(item.into())
     }
 
     async fn query_direct(
-        &mut self,
+        &self,
         query: &str,
         _matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         // empty query should show empty result
         if query.is_empty() {
-            return Ok(PluginQueryOutput::One(self.item("".to_string())));
+            return Ok(self.item("".to_string()).into());
         }
 
         let mut ctx = sci_calc::context::Context::new();
@@ -77,6 +82,6 @@ impl kal_plugin::Plugin for Plugin {
 
         let item = self.item(result);
 
-        Ok(PluginQueryOutput::One(item))
+        Ok(item.into())
     }
 }
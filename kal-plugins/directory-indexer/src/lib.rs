@@ -1,45 +1,55 @@
 use std::ffi::OsString;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use kal_config::Config;
-use kal_plugin::{Action, Icon, IntoResultItem, PluginQueryOutput, ResultItem};
-use kal_utils::{IteratorExt, PathExt};
+use kal_plugin::{
+    ActionFuture, ActionProgress, Candidate, Highlights, Icon, Index, IndexingStatus,
+    IntoResultItem, PathPayload, PluginHandle, PluginQueryOutput, ResultItem,
+};
+use kal_utils::PathExt;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use smol::stream::*;
 
 #[derive(Debug)]
 pub struct Plugin {
     paths: Vec<String>,
-    entries: Vec<DirEntry>,
+    entries: Index<DirEntry>,
+    handle: PluginHandle,
+    /// Indexing in the background, dropping it cancels indexing.
+    indexing: Option<smol::Task<()>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
+#[schemars(rename = "DirectoryIndexerConfig")]
 struct PluginConfig {
+    /// Directories whose entries are indexed, may contain `%VAR%` environment variables.
     #[serde(default)]
     paths: Vec<String>,
 }
 
 impl Plugin {
     const NAME: &'static str = "DirectoryIndexer";
+    const QUERY_CHUNK_SIZE: usize = 1024;
 
     fn update_config(&mut self, config: &Config) {
         let config = config.plugin_config::<PluginConfig>(Self::NAME);
         self.paths = config.paths;
     }
 
-    async fn index_dirs(&mut self) {
-        self.entries.clear();
+    /// Indexes the paths again in the background and swaps the new entries in once done.
+    fn index_dirs(&mut self) {
+        let paths = self.paths.clone();
+        let handle = self.handle.clone();
 
-        let expanded_paths = self.paths.iter().map(PathExt::replace_env);
-        let mut entries = smol::stream::iter(expanded_paths).map(read_dir);
+        let indexing = self.entries.reindex(self.handle.clone(), async move {
+            Ok(read_dirs(&paths, &handle).await)
+        });
 
-        while let Some(e) = entries.next().await {
-            if let Ok(e) = e.await {
-                let e = e.iter().map(|e| DirEntry::new(e.path()));
-                self.entries.extend(e);
-            }
-        }
+        // cancels indexing outdated paths
+        self.indexing.replace(indexing);
     }
 }
 
@@ -50,48 +60,123 @@ impl kal_plugin::Plugin for Plugin {
 
         Self {
             paths: config.paths,
-            entries: Vec::new(),
+            entries: Index::new(),
+            handle: PluginHandle::default(),
+            indexing: None,
         }
     }
 
+    fn config_schema(schema: &mut kal_config::ConfigSchema) {
+        schema.plugin::<PluginConfig>(Self::NAME);
+    }
+
     fn name(&self) -> &'static str {
         Self::NAME
     }
 
+    fn init_cache(&mut self, cache_dir: &Path) {
+        let path = cache_dir.join(format!("{}.json", Self::NAME));
+        if let Err(e) = self.entries.load_cache(path) {
+            tracing::error!("[DirectoryIndexer] Failed to load cached index: {e}");
+        }
+    }
+
+    fn init_handle(&mut self, handle: PluginHandle) {
+        self.handle = handle;
+    }
+
     async fn reload(&mut self, config: &Config) -> anyhow::Result<()> {
         self.update_config(config);
-        self.index_dirs().await;
+        self.index_dirs();
         Ok(())
     }
 
     async fn query(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
-        Ok(self
-            .entries
-            .iter()
-            .filter_map(|entry| entry.fuzzy_match(query, matcher))
-            .collect_non_empty::<Vec<_>>()
-            .into())
+        // the index can be huge, so results are only built for the matches that are shown
+        let mut results = Vec::new();
+        let mut matches = Vec::new();
+
+        let entries = self.entries.snapshot(matcher);
+
+        // when typing, only the entries that matched the previous query can match
+        let candidates = matcher.candidates(query, entries.len());
+
+        for candidates in candidates.chunks(Self::QUERY_CHUNK_SIZE) {
+            if cancellation.is_cancelled() {
+                return Ok(PluginQueryOutput::None);
+            }
+
+            for &idx in candidates {
+                let entry = &entries[idx];
+                if let Some(score) = entry.fuzzy_score(query, matcher) {
+                    let id = entry.id.clone();
+                    results.push(Candidate {
+                        key: idx,
+                        id,
+                        score,
+                    });
+                    matches.push(idx);
+                }
+            }
+
+            // large indexes can take a while to match,
+            // yield so the query timeout or cancellation can kick in
+            smol::future::yield_now().await;
+        }
+
+        matcher.cache_matches(query, entries.len(), matches);
+
+        Ok(results.into())
+    }
+
+    fn materialize(
+        &self,
+        candidate: &Candidate,
+        query: &str,
+        matcher: &mut kal_plugin::FuzzyMatcher,
+    ) -> Option<ResultItem> {
+        let entries = self.entries.items();
+
+        // the index may have been swapped since the query
+        let entry = entries.get(candidate.key)?;
+        if entry.id != candidate.id {
+            return None;
+        }
+
+        entry.fuzzy_match(query, matcher)
+    }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let run = || serde_json::from_value::<PathPayload>(payload)?.run(action);
+        kal_plugin::ready_outcome(run())
     }
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug)]
 struct DirEntry {
     name: OsString,
     path: PathBuf,
     is_dir: bool,
-    id: String,
+    id: Arc<str>,
 }
 
 impl DirEntry {
     fn new(path: PathBuf) -> Self {
         let name = path.file_stem().unwrap_or_default().to_os_string();
-        let filename = path.file_name().unwrap_or_default().to_os_string();
         let is_dir = path.is_dir();
-        let id = format!("{}:{}", Plugin::NAME, filename.to_string_lossy());
+        // the launch history is keyed by id, so same-named
+        // entries of different directories need different ids
+        let id = format!("{}:{}", Plugin::NAME, path.to_string_lossy()).into();
         Self {
             name,
             is_dir,
@@ -100,44 +185,20 @@ impl DirEntry {
         }
     }
 
-    fn item(&self, score: u16) -> ResultItem {
-        let actions = if self.is_dir {
-            vec![
-                {
-                    let path = self.path.clone();
-                    Action::primary(move |_| kal_utils::open_dir(&path))
-                },
-                {
-                    let path = self.path.clone();
-                    Action::open_location(move |_| kal_utils::reveal_item_in_dir(&path))
-                },
-            ]
-        } else {
-            vec![
-                {
-                    let path = self.path.clone();
-                    Action::primary(move |_| kal_utils::execute(&path, false))
-                },
-                {
-                    let path = self.path.clone();
-                    Action::open_elevated(move |_| kal_utils::execute(&path, true))
-                },
-                {
-                    let path = self.path.clone();
-                    Action::open_location(move |_| kal_utils::reveal_item_in_dir(&path))
-                },
-            ]
-        };
-
-        ResultItem {
-            id: self.id.as_str().into(),
+    fn item(&self, score: u16, highlights: Highlights) -> Option<ResultItem> {
+        let payload = PathPayload::new(&self.path, self.is_dir);
+
+        Some(ResultItem {
+            id: self.id.to_string(),
             icon: Icon::extract_path(self.path.to_string_lossy()),
             primary_text: self.name.to_string_lossy().into_owned(),
             secondary_text: self.path.to_string_lossy().into_owned(),
             tooltip: None,
-            actions,
+            actions: payload.actions(),
             score,
-        }
+            highlights,
+            payload: payload.to_value()?,
+        })
     }
 }
 
@@ -147,13 +208,42 @@ impl IntoResultItem for DirEntry {
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
     ) -> Option<ResultItem> {
+        matcher
+            .fuzzy_indices(&self.name.to_string_lossy(), query)
+            .map(|m| (m.score, Highlights::primary(m.ranges)))
+            .or_else(|| {
+                matcher
+                    .fuzzy_indices(&self.path.to_string_lossy(), query)
+                    .map(|m| (m.score, Highlights::secondary(m.ranges)))
+            })
+            .and_then(|(score, highlights)| self.item(score, highlights))
+    }
+
+    fn fuzzy_score(&self, query: &str, matcher: &mut kal_plugin::FuzzyMatcher) -> Option<u16> {
         matcher
             .fuzzy_match(&self.name.to_string_lossy(), query)
             .or_else(|| matcher.fuzzy_match(&self.path.to_string_lossy(), query))
-            .map(|score| self.item(score))
     }
 }
 
+async fn read_dirs(paths: &[String], handle: &PluginHandle) -> Vec<DirEntry> {
+    let expanded_paths = paths.iter().map(PathExt::replace_env);
+    let mut dirs = smol::stream::iter(expanded_paths).map(read_dir);
+
+    let mut entries = Vec::new();
+
+    while let Some(e) = dirs.next().await {
+        if let Ok(e) = e.await {
+            entries.extend(e.iter().map(|e| DirEntry::new(e.path())));
+        }
+
+        let scanned = entries.len();
+        handle.report_indexing(IndexingStatus::Indexing { scanned });
+    }
+
+    entries
+}
+
 async fn read_dir<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<smol::fs::DirEntry>> {
     let mut entries = smol::fs::read_dir(path).await?;
     let mut out = Vec::with_capacity(entries.size_hint().0);
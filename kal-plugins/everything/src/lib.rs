@@ -1,10 +1,14 @@
 use std::ffi::OsString;
-use std::os::windows::process::CommandExt;
 use std::path::PathBuf;
 
 use kal_config::Config;
-use kal_plugin::{Action, Icon, IntoResultItem, PluginQueryOutput, ResultItem};
+use kal_plugin::{
+    ActionFuture, ActionProgress, Highlights, Icon, IntoResultItem, PathPayload, PluginQueryOutput,
+    ResultItem,
+};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use smol::process::windows::CommandExt;
 
 #[derive(Debug)]
 pub struct Plugin {
@@ -12,9 +16,11 @@ pub struct Plugin {
     max_results: usize,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
+#[schemars(rename = "EverythingConfig")]
 struct PluginConfig {
+    /// Path to Everything's `es.exe` command line interface, looked up in `PATH` by default.
     es: Option<PathBuf>,
 }
 
@@ -34,6 +40,10 @@ impl kal_plugin::Plugin for Plugin {
         }
     }
 
+    fn config_schema(schema: &mut kal_config::ConfigSchema) {
+        schema.plugin::<PluginConfig>(Self::NAME);
+    }
+
     fn name(&self) -> &'static str {
         Self::NAME
     }
@@ -43,6 +53,8 @@ impl kal_plugin::Plugin for Plugin {
             enabled: Some(true),
             include_in_global_results: Some(false),
             direct_activation_command: Some("?".into()),
+            // `es` is a separate process, give it more time
+            query_timeout: Some(1000),
             inner: toml::Table::try_from(PluginConfig::default()).ok(),
         }
     }
@@ -55,23 +67,26 @@ impl kal_plugin::Plugin for Plugin {
     }
 
     async fn query_direct(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         if query.is_empty() {
             return Ok(PluginQueryOutput::None);
         }
 
-        let mut cmd = std::process::Command::new(&self.es);
+        let mut cmd = smol::process::Command::new(&self.es);
         cmd.arg("-max-results")
             .arg(self.max_results.to_string())
-            .arg(query);
+            .arg(query)
+            // don't leave `es` running if the query times out
+            .kill_on_drop(true);
 
         const CREATE_NO_WINDOW: u32 = 0x08000000;
         cmd.creation_flags(CREATE_NO_WINDOW);
 
-        let output = cmd.output()?;
+        let output = cmd.output().await?;
 
         match output.status.success() {
             true => {}
@@ -91,6 +106,16 @@ impl kal_plugin::Plugin for Plugin {
             .collect::<Vec<_>>()
             .into())
     }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let run = || serde_json::from_value::<PathPayload>(payload)?.run(action);
+        kal_plugin::ready_outcome(run())
+    }
 }
 
 #[derive(Debug)]
@@ -106,7 +131,7 @@ impl EverythingEntry {
         let path = PathBuf::from(path);
         let name = path.file_name().unwrap_or_default().to_os_string();
         let is_dir = path.is_dir();
-        let id = format!("{}:{}", Plugin::NAME, name.to_string_lossy());
+        let id = format!("{}:{}", Plugin::NAME, path.to_string_lossy());
         Self {
             name,
             path,
@@ -119,36 +144,21 @@ impl EverythingEntry {
 impl IntoResultItem for EverythingEntry {
     fn fuzzy_match(
         &self,
-        _query: &str,
-        _matcher: &mut kal_plugin::FuzzyMatcher,
+        query: &str,
+        matcher: &mut kal_plugin::FuzzyMatcher,
     ) -> Option<ResultItem> {
-        let actions = if self.is_dir {
-            vec![
-                {
-                    let path = self.path.clone();
-                    Action::primary(move |_| kal_utils::open_dir(&path))
-                },
-                {
-                    let path = self.path.clone();
-                    Action::open_location(move |_| kal_utils::reveal_item_in_dir(&path))
-                },
-            ]
-        } else {
-            vec![
-                {
-                    let path = self.path.clone();
-                    Action::primary(move |_| kal_utils::execute(&path, false))
-                },
-                {
-                    let path = self.path.clone();
-                    Action::open_elevated(move |_| kal_utils::execute(&path, true))
-                },
-                {
-                    let path = self.path.clone();
-                    Action::open_location(move |_| kal_utils::reveal_item_in_dir(&path))
-                },
-            ]
-        };
+        let payload = PathPayload::new(&self.path, self.is_dir);
+
+        // Everything already did the search, the matcher only finds what to highlight
+        let highlights = matcher
+            .fuzzy_indices(&self.name.to_string_lossy(), query)
+            .map(|m| Highlights::primary(m.ranges))
+            .or_else(|| {
+                matcher
+                    .fuzzy_indices(&self.path.to_string_lossy(), query)
+                    .map(|m| Highlights::secondary(m.ranges))
+            })
+            .unwrap_or_default();
 
         Some(ResultItem {
             id: self.id.as_str().into(),
@@ -156,8 +166,10 @@ impl IntoResultItem for EverythingEntry {
             primary_text: self.name.to_string_lossy().into_owned(),
             secondary_text: self.path.to_string_lossy().into_owned(),
             tooltip: None,
-            actions,
+            actions: payload.actions(),
             score: 0,
+            highlights,
+            payload: payload.to_value()?,
         })
     }
 }
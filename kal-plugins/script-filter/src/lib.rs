@@ -0,0 +1,266 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use kal_config::Config;
+use kal_plugin::{
+    Action, ActionFuture, ActionProgress, BuiltinIcon, Icon, PluginQueryOutput, ResultItem,
+};
+use kal_utils::PathExt;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug)]
+pub struct Plugin {
+    filters: Vec<ScriptFilter>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+#[schemars(rename = "ScriptFilterConfig")]
+struct PluginConfig {
+    /// Commands to run on queries starting with their keyword.
+    #[serde(default)]
+    filters: Vec<ScriptFilter>,
+}
+
+impl Plugin {
+    const NAME: &'static str = "ScriptFilter";
+
+    /// Environment variable the query is passed to scripts in `Shell` mode through.
+    const QUERY_ENV: &'static str = "KAL_QUERY";
+}
+
+#[async_trait::async_trait]
+impl kal_plugin::Plugin for Plugin {
+    fn new(config: &Config) -> Self {
+        let config = config.plugin_config::<PluginConfig>(Self::NAME);
+        Self {
+            filters: config.filters,
+        }
+    }
+
+    fn config_schema(schema: &mut kal_config::ConfigSchema) {
+        schema.plugin::<PluginConfig>(Self::NAME);
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn default_plugin_config(&self) -> kal_config::PluginConfig {
+        kal_config::PluginConfig {
+            enabled: Some(true),
+            // filters only run when their keyword is typed
+            include_in_global_results: Some(false),
+            direct_activation_command: None,
+            // scripts are separate processes and often hit the network, give them more time
+            query_timeout: Some(2000),
+            inner: None,
+        }
+    }
+
+    async fn reload(&mut self, config: &Config) -> anyhow::Result<()> {
+        let config = config.plugin_config::<PluginConfig>(Self::NAME);
+        self.filters = config.filters;
+        Ok(())
+    }
+
+    fn direct_activation_keywords(&self) -> Vec<String> {
+        self.filters.iter().map(|f| f.keyword.clone()).collect()
+    }
+
+    async fn query_direct(
+        &self,
+        query: &str,
+        _matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
+    ) -> anyhow::Result<PluginQueryOutput> {
+        // the keyword that invoked the plugin is kept in the query
+        let (keyword, query) = query.split_once(char::is_whitespace).unwrap_or((query, ""));
+
+        let Some(filter) = self.filters.iter().find(|f| f.keyword == keyword) else {
+            return Ok(PluginQueryOutput::None);
+        };
+
+        // the script is killed if the query is dropped, like when the user types on
+        filter.run(query.trim()).await.map(Into::into)
+    }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let run = || {
+            let arg = serde_json::from_value::<String>(payload)?;
+            match action {
+                Action::PRIMARY => kal_utils::execute(&arg, false),
+                Action::RUN_ELEVATED => kal_utils::execute(&arg, true),
+                Action::OPEN_LOCATION => kal_utils::reveal_item_in_dir(&arg),
+                _ => anyhow::bail!("Unknown action `{action}`"),
+            }
+        };
+
+        kal_plugin::ready_outcome(run())
+    }
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, JsonSchema)]
+enum ScriptFilterMode {
+    /// Run `command` as an executable, passing `args` followed by the query.
+    #[default]
+    Exec,
+    /// Run `command` as a script through `shell`, the query is passed
+    /// in the `KAL_QUERY` environment variable, like `$env:KAL_QUERY` in PowerShell.
+    Shell,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+struct ScriptFilter {
+    /// Queries starting with this word are passed to `command`, without it.
+    keyword: String,
+    /// Executable or script to run, it should print a JSON list of items to stdout.
+    command: String,
+    /// Arguments passed before the query.
+    #[serde(default)]
+    args: Vec<String>,
+    /// How `command` is run.
+    #[serde(default)]
+    mode: ScriptFilterMode,
+    /// Shell to run `command` through in `Shell` mode.
+    shell: Option<String>,
+    /// Directory to run `command` in.
+    working_directory: Option<PathBuf>,
+}
+
+impl ScriptFilter {
+    async fn run(&self, query: &str) -> anyhow::Result<Vec<ResultItem>> {
+        let cwd = self.working_directory.as_ref().map(|p| p.replace_env());
+
+        let stdout = match self.mode {
+            ScriptFilterMode::Exec => {
+                let command = PathBuf::from(&self.command).replace_env();
+                let args = self.args.iter().map(String::as_str).chain([query]);
+                kal_utils::execute_with_output(command, args, cwd).await?
+            }
+            ScriptFilterMode::Shell => {
+                // the query isn't spliced into the script, since each shell quotes differently
+                kal_utils::execute_in_shell_with_output(
+                    self.shell.as_deref(),
+                    self.command.as_str(),
+                    cwd,
+                    &[(Plugin::QUERY_ENV, query)],
+                )
+                .await?
+            }
+        };
+
+        let items = match serde_json::from_str(&stdout)? {
+            ScriptOutput::Items { items } => items,
+            ScriptOutput::List(items) => items,
+        };
+
+        // ids identify the item to run its actions and rank it by how often it's launched,
+        // so they must be unique but also stay the same across queries
+        let mut ids = HashSet::new();
+
+        Ok(items
+            .into_iter()
+            .enumerate()
+            .map(|(idx, item)| {
+                let mut id = item.id(&self.keyword);
+                if !ids.insert(id.clone()) {
+                    id = format!("{id}:{idx}");
+                }
+
+                item.into_result_item(id, idx)
+            })
+            .collect())
+    }
+}
+
+/// Output of a script filter, either a list of items
+/// or an object with an `items` field like Alfred's.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptOutput {
+    Items { items: Vec<ScriptItem> },
+    List(Vec<ScriptItem>),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptIcon {
+    Path(String),
+    Icon(Icon),
+}
+
+#[derive(Deserialize)]
+enum ScriptAction {
+    RunElevated,
+    OpenLocation,
+}
+
+#[derive(Deserialize)]
+struct ScriptItem {
+    /// Identifies the item across queries, defaults to `arg` or `title`.
+    uid: Option<String>,
+    title: String,
+    #[serde(default)]
+    subtitle: String,
+    icon: Option<ScriptIcon>,
+    /// What to execute when the item is activated, a path, an app or a url.
+    arg: Option<String>,
+    /// Extra actions on top of the primary action.
+    #[serde(default)]
+    actions: Vec<ScriptAction>,
+}
+
+impl ScriptItem {
+    fn id(&self, keyword: &str) -> String {
+        let uid = self
+            .uid
+            .as_ref()
+            .or(self.arg.as_ref())
+            .unwrap_or(&self.title);
+        format!("{}:{keyword}:{uid}", Plugin::NAME)
+    }
+
+    fn into_result_item(self, id: String, idx: usize) -> ResultItem {
+        let icon = match self.icon {
+            Some(ScriptIcon::Path(path)) => Icon::path(path),
+            Some(ScriptIcon::Icon(icon)) => icon,
+            None => BuiltinIcon::Shell.into(),
+        };
+
+        // items without an arg are informational and have nothing to run
+        let actions = match self.arg {
+            Some(_) => {
+                let secondary = self.actions.iter().map(|action| match action {
+                    ScriptAction::RunElevated => Action::dispatch(Action::RUN_ELEVATED),
+                    ScriptAction::OpenLocation => Action::dispatch(Action::OPEN_LOCATION),
+                });
+
+                std::iter::once(Action::dispatch(Action::PRIMARY))
+                    .chain(secondary)
+                    .collect()
+            }
+            None => vec![],
+        };
+
+        ResultItem {
+            id,
+            icon,
+            primary_text: self.title,
+            secondary_text: self.subtitle,
+            tooltip: None,
+            actions,
+            // the keyword was typed explicitly, keep the script's order on top
+            score: u16::MAX.saturating_sub(idx as u16),
+            highlights: Default::default(),
+            payload: self.arg.into(),
+        }
+    }
+}
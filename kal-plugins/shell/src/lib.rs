@@ -1,5 +1,6 @@
 use kal_config::Config;
 use kal_plugin::{Action, BuiltinIcon, PluginQueryOutput, ResultItem};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug)]
@@ -8,10 +9,13 @@ pub struct Plugin {
     no_exit: bool,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
+#[schemars(rename = "ShellConfig")]
 struct PluginConfig {
+    /// Shell to run commands in.
     shell: Option<Shell>,
+    /// Whether to keep the shell open after the command exits.
     no_exit: Option<bool>,
 }
 
@@ -31,6 +35,10 @@ impl kal_plugin::Plugin for Plugin {
         }
     }
 
+    fn config_schema(schema: &mut kal_config::ConfigSchema) {
+        schema.plugin::<PluginConfig>(Self::NAME);
+    }
+
     fn name(&self) -> &'static str {
         Self::NAME
     }
@@ -40,6 +48,7 @@ impl kal_plugin::Plugin for Plugin {
             enabled: Some(true),
             include_in_global_results: Some(false),
             direct_activation_command: Some(">".into()),
+            query_timeout: None,
             inner: None,
         }
     }
@@ -52,15 +61,16 @@ impl kal_plugin::Plugin for Plugin {
     }
 
     async fn query_direct(
-        &mut self,
+        &self,
         query: &str,
         _matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         Ok(self.shell.item(query.to_string(), self.no_exit).into())
     }
 }
 
-#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug)]
+#[derive(Clone, Copy, Default, Serialize, Deserialize, Debug, JsonSchema)]
 #[allow(clippy::enum_variant_names)]
 enum Shell {
     #[default]
@@ -113,6 +123,8 @@ impl Shell {
                 }),
             ],
             score: 0,
+            highlights: Default::default(),
+            payload: Default::default(),
         }
     }
 }
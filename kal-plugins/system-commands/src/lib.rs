@@ -1,6 +1,10 @@
 use kal_config::Config;
-use kal_plugin::{Action, BuiltinIcon, Icon, IntoResultItem, PluginQueryOutput, ResultItem};
+use kal_plugin::{
+    Action, ActionFuture, ActionProgress, BuiltinIcon, Highlights, Icon, IntoResultItem,
+    PluginQueryOutput, ResultItem,
+};
 use kal_utils::IteratorExt;
+use serde::{Deserialize, Serialize};
 use strum::AsRefStr;
 
 #[derive(Debug)]
@@ -14,7 +18,7 @@ impl Plugin {
     fn all(&self) -> Option<Vec<ResultItem>> {
         self.commands
             .iter()
-            .map(|workflow| workflow.item(0))
+            .map(|workflow| workflow.item(0, Highlights::default()))
             .collect_non_empty()
     }
 
@@ -47,6 +51,7 @@ impl kal_plugin::Plugin for Plugin {
             enabled: Some(true),
             include_in_global_results: Some(true),
             direct_activation_command: Some("!".into()),
+            query_timeout: None,
             inner: None,
         }
     }
@@ -56,17 +61,19 @@ impl kal_plugin::Plugin for Plugin {
     }
 
     async fn query(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         Ok(self.all_for_query(query, matcher).into())
     }
 
     async fn query_direct(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         if query.is_empty() {
             Ok(self.all().into())
@@ -74,9 +81,26 @@ impl kal_plugin::Plugin for Plugin {
             Ok(self.all_for_query(query, matcher).into())
         }
     }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let run = || {
+            if action != Action::PRIMARY {
+                anyhow::bail!("Unknown action `{action}`");
+            }
+
+            serde_json::from_value::<SystemCommand>(payload)?.execute()
+        };
+
+        kal_plugin::ready_outcome(run())
+    }
 }
 
-#[derive(Clone, Copy, Debug, AsRefStr)]
+#[derive(Clone, Copy, Debug, AsRefStr, Serialize, Deserialize)]
 enum SystemCommand {
     Shutdown,
     Restart,
@@ -226,16 +250,17 @@ impl SystemCommand {
         unimplemented!()
     }
 
-    fn item(&self, score: u16) -> ResultItem {
-        let system_command = *self;
+    fn item(&self, score: u16, highlights: Highlights) -> ResultItem {
         ResultItem {
             id: self.id().into(),
             icon: self.icon(),
             primary_text: self.as_ref().into(),
             secondary_text: self.description().into(),
             tooltip: None,
-            actions: vec![Action::primary(move |_| system_command.execute())],
+            actions: vec![Action::dispatch(Action::PRIMARY)],
             score,
+            highlights,
+            payload: serde_json::to_value(self).unwrap_or_default(),
         }
     }
 }
@@ -247,7 +272,7 @@ impl IntoResultItem for SystemCommand {
         matcher: &mut kal_plugin::FuzzyMatcher,
     ) -> Option<ResultItem> {
         matcher
-            .fuzzy_match(self.as_ref(), query)
-            .map(|score| self.item(score))
+            .fuzzy_indices(self.as_ref(), query)
+            .map(|m| self.item(m.score, Highlights::primary(m.ranges)))
     }
 }
@@ -1,46 +1,28 @@
 use std::path::PathBuf;
 
 use kal_config::Config;
-use kal_plugin::{Action, BuiltinIcon, Icon, IntoResultItem, PluginQueryOutput, ResultItem};
+use kal_plugin::{
+    Action, ActionFuture, ActionProgress, BuiltinIcon, Highlights, Icon, Index, IntoResultItem,
+    PluginQueryOutput, ResultItem,
+};
 use kal_utils::IteratorExt;
 use serde::Deserialize;
 use sqlite::OpenFlags;
 
 #[derive(Debug)]
 pub struct Plugin {
-    workspaces: Vec<Workspace>,
+    workspaces: Index<Workspace>,
 }
 
 impl Plugin {
     const NAME: &str = "VSCodeWorkspaces";
     const WORKSPACES_QUERY: &str =
         "SELECT value FROM ItemTable WHERE key LIKE 'history.recentlyOpenedPathsList'";
-}
-
-#[async_trait::async_trait]
-impl kal_plugin::Plugin for Plugin {
-    fn new(_config: &Config) -> Self {
-        Self {
-            workspaces: Vec::new(),
-        }
-    }
 
-    fn name(&self) -> &'static str {
-        Self::NAME
-    }
-
-    fn default_plugin_config(&self) -> kal_config::PluginConfig {
-        kal_config::PluginConfig {
-            enabled: Some(true),
-            include_in_global_results: Some(false),
-            direct_activation_command: Some("{".into()),
-            inner: None,
-        }
-    }
-
-    async fn reload(&mut self, _config: &Config) -> anyhow::Result<()> {
+    /// Reads the recently opened workspaces from VSCode's state database.
+    fn read_workspaces() -> anyhow::Result<Vec<Workspace>> {
         let Some(roaming) = dirs::data_dir() else {
-            return Ok(());
+            return Ok(Vec::new());
         };
 
         let vscode_appdata = roaming.join("Code");
@@ -64,28 +46,83 @@ impl kal_plugin::Plugin for Plugin {
             None => BuiltinIcon::Code.into(),
         };
 
-        self.workspaces = workspaces
+        let workspaces = workspaces
             .entries
             .into_iter()
             .filter(|w| w.folder_uri.is_some())
             .map(|w| Workspace::new(w.folder_uri.unwrap(), icon.clone()))
             .collect();
 
+        Ok(workspaces)
+    }
+}
+
+#[async_trait::async_trait]
+impl kal_plugin::Plugin for Plugin {
+    fn new(_config: &Config) -> Self {
+        Self {
+            workspaces: Index::new(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        Self::NAME
+    }
+
+    fn default_plugin_config(&self) -> kal_config::PluginConfig {
+        kal_config::PluginConfig {
+            enabled: Some(true),
+            include_in_global_results: Some(false),
+            direct_activation_command: Some("{".into()),
+            query_timeout: None,
+            inner: None,
+        }
+    }
+
+    async fn reload(&mut self, _config: &Config) -> anyhow::Result<()> {
+        self.workspaces.replace(Self::read_workspaces()?);
+        Ok(())
+    }
+
+    async fn on_show(&self) -> anyhow::Result<()> {
+        // workspaces opened in VSCode since kal was last shown
+        let workspaces = smol::unblock(Self::read_workspaces).await?;
+        self.workspaces.replace(workspaces);
         Ok(())
     }
 
     async fn query_direct(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         Ok(self
             .workspaces
+            .items()
             .iter()
             .filter_map(|w| w.fuzzy_match(query, matcher))
             .collect_non_empty::<Vec<_>>()
             .into())
     }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let run = || {
+            if action != Action::PRIMARY {
+                anyhow::bail!("Unknown action `{action}`");
+            }
+
+            let uri = serde_json::from_value::<String>(payload)?;
+            kal_utils::execute_with_args("code", format!("--folder-uri {uri}"), false, true)
+        };
+
+        kal_plugin::ready_outcome(run())
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -135,24 +172,17 @@ impl Workspace {
 }
 
 impl Workspace {
-    fn item(&self, score: u16) -> ResultItem {
-        let uri = self.uri.clone();
-
+    fn item(&self, score: u16, highlights: Highlights) -> ResultItem {
         ResultItem {
             id: format!("{}:{}", Plugin::NAME, self.name),
             icon: self.icon.clone(),
             primary_text: self.name.clone(),
             secondary_text: self.path.to_string_lossy().to_string(),
             tooltip: None,
-            actions: vec![Action::primary(move |_| {
-                kal_utils::execute_with_args(
-                    "code",
-                    format!("--folder-uri {}", uri.as_str()),
-                    false,
-                    true,
-                )
-            })],
+            actions: vec![Action::dispatch(Action::PRIMARY)],
             score,
+            highlights,
+            payload: self.uri.as_str().into(),
         }
     }
 }
@@ -164,7 +194,7 @@ impl IntoResultItem for Workspace {
         matcher: &mut kal_plugin::FuzzyMatcher,
     ) -> Option<ResultItem> {
         matcher
-            .fuzzy_match(&self.name, query)
-            .map(|score| self.item(score))
+            .fuzzy_indices(&self.name, query)
+            .map(|m| self.item(m.score, Highlights::primary(m.ranges)))
     }
 }
@@ -1,8 +1,12 @@
 use std::path::PathBuf;
 
 use kal_config::Config;
-use kal_plugin::{Action, BuiltinIcon, Icon, IntoResultItem, PluginQueryOutput, ResultItem};
+use kal_plugin::{
+    Action, ActionFuture, ActionProgress, BuiltinIcon, Highlights, Icon, IntoResultItem,
+    PluginQueryOutput, ResultItem,
+};
 use kal_utils::{IteratorExt, PathExt};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use url::Url;
 
@@ -11,9 +15,11 @@ pub struct Plugin {
     workflows: Vec<Workflow>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, JsonSchema)]
 #[serde(deny_unknown_fields)]
+#[schemars(rename = "WorkflowsConfig")]
 struct PluginConfig {
+    /// Workflows to list, each running its steps in order.
     #[serde(default)]
     workflows: Vec<Workflow>,
 }
@@ -34,7 +40,7 @@ impl Plugin {
     fn all(&self) -> Option<Vec<ResultItem>> {
         self.workflows
             .iter()
-            .map(|workflow| workflow.item(0))
+            .map(|workflow| workflow.item(0, Highlights::default()))
             .collect_non_empty()
     }
 
@@ -64,6 +70,10 @@ impl kal_plugin::Plugin for Plugin {
         plugin
     }
 
+    fn config_schema(schema: &mut kal_config::ConfigSchema) {
+        schema.plugin::<PluginConfig>(Self::NAME);
+    }
+
     fn name(&self) -> &'static str {
         Self::NAME
     }
@@ -73,6 +83,7 @@ impl kal_plugin::Plugin for Plugin {
             enabled: Some(true),
             include_in_global_results: Some(true),
             direct_activation_command: Some("@".into()),
+            query_timeout: None,
             inner: None,
         }
     }
@@ -87,17 +98,19 @@ impl kal_plugin::Plugin for Plugin {
     }
 
     async fn query(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         Ok(self.all_for_query(query, matcher).into())
     }
 
     async fn query_direct(
-        &mut self,
+        &self,
         query: &str,
         matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
     ) -> anyhow::Result<PluginQueryOutput> {
         if query.is_empty() {
             Ok(self.all().into())
@@ -105,17 +118,47 @@ impl kal_plugin::Plugin for Plugin {
             Ok(self.all_for_query(query, matcher).into())
         }
     }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        progress: ActionProgress,
+    ) -> ActionFuture {
+        let find = || {
+            let elevated = match action {
+                Action::PRIMARY => false,
+                Action::RUN_ELEVATED => true,
+                _ => anyhow::bail!("Unknown action `{action}`"),
+            };
+
+            let id = payload.as_str().unwrap_or_default();
+            let Some(workflow) = self.workflows.iter().find(|w| w.id == id) else {
+                anyhow::bail!("Couldn't find workflow: {id}");
+            };
+
+            Ok((workflow.clone(), elevated))
+        };
+
+        let workflow = find();
+        Box::pin(async move {
+            let (workflow, elevated) = workflow?;
+            workflow.execute(elevated, progress).await.map(Into::into)
+        })
+    }
 }
 
-#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq)]
+#[derive(Clone, Deserialize, Serialize, Debug, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "camelCase", untagged)]
 enum WorkflowStep {
-    Path {
-        path: PathBuf,
-    },
+    /// Opens a file or directory.
+    Path { path: PathBuf },
+    /// Opens a URL in the default browser.
     Url {
+        #[schemars(with = "String")]
         url: Url,
     },
+    /// Runs a script in a shell.
     Shell {
         shell: Option<String>,
         script: String,
@@ -124,7 +167,31 @@ enum WorkflowStep {
     },
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+impl WorkflowStep {
+    fn execute(&self, elevated: bool) -> anyhow::Result<()> {
+        match self {
+            WorkflowStep::Path { path, .. } => {
+                let path = path.replace_env();
+                kal_utils::execute(path, elevated)
+            }
+            WorkflowStep::Url { url } => kal_utils::open_url(url),
+            WorkflowStep::Shell {
+                shell,
+                script,
+                working_directory,
+                hidden,
+            } => kal_utils::execute_in_shell(
+                shell.as_ref(),
+                script,
+                working_directory.as_ref(),
+                *hidden,
+                elevated,
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
 struct Workflow {
     name: String,
     #[serde(default)]
@@ -156,51 +223,37 @@ impl Workflow {
         res == rfd::MessageDialogResult::Yes
     }
 
-    fn execute(&self, elevated: bool) -> anyhow::Result<()> {
-        if !self.confirmed() {
+    /// Runs the steps one by one off the async executor, reporting each step as it starts.
+    async fn execute(self, elevated: bool, progress: ActionProgress) -> anyhow::Result<()> {
+        let workflow = self.clone();
+        if !smol::unblock(move || workflow.confirmed()).await {
             return Ok(());
         }
 
-        for step in &self.steps {
-            match step {
-                WorkflowStep::Path { path, .. } => {
-                    let path = path.replace_env();
-                    kal_utils::execute(path, elevated)?
-                }
-                WorkflowStep::Url { url } => kal_utils::open_url(url)?,
-                WorkflowStep::Shell {
-                    shell,
-                    script,
-                    working_directory,
-                    hidden,
-                } => kal_utils::execute_in_shell(
-                    shell.as_ref(),
-                    script,
-                    working_directory.as_ref(),
-                    *hidden,
-                    elevated,
-                )?,
-            }
+        let count = self.steps.len();
+        for (idx, step) in self.steps.into_iter().enumerate() {
+            progress.report(format!("{}: step {}/{count}", self.name, idx + 1));
+            smol::unblock(move || step.execute(elevated)).await?;
         }
 
         Ok(())
     }
 
-    fn item(&self, score: u16) -> ResultItem {
-        let workflow = self.clone();
-        let open = Action::primary(move |_| workflow.execute(false));
-
-        let workflow = self.clone();
-        let open_elevated = Action::open_elevated(move |_| workflow.execute(true));
-
+    fn item(&self, score: u16, highlights: Highlights) -> ResultItem {
         ResultItem {
             id: self.id.as_str().into(),
             icon: self.icon(),
             primary_text: self.name.as_str().into(),
             secondary_text: self.description.as_deref().unwrap_or("Workflow").into(),
             tooltip: None,
-            actions: vec![open, open_elevated],
+            // workflows run in the background and report the step they are running
+            actions: vec![
+                Action::dispatch_async(Action::PRIMARY),
+                Action::dispatch_async(Action::RUN_ELEVATED),
+            ],
             score,
+            highlights,
+            payload: self.id.as_str().into(),
         }
     }
 }
@@ -212,12 +265,14 @@ impl IntoResultItem for Workflow {
         matcher: &mut kal_plugin::FuzzyMatcher,
     ) -> Option<ResultItem> {
         matcher
-            .fuzzy_match(&self.name, query)
+            .fuzzy_indices(&self.name, query)
+            .map(|m| (m.score, Highlights::primary(m.ranges)))
             .or_else(|| {
                 self.description
                     .as_ref()
-                    .and_then(|description| matcher.fuzzy_match(description, query))
+                    .and_then(|description| matcher.fuzzy_indices(description, query))
+                    .map(|m| (m.score, Highlights::secondary(m.ranges)))
             })
-            .map(|score| self.item(score))
+            .map(|(score, highlights)| self.item(score, highlights))
     }
 }
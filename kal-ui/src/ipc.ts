@@ -1,4 +1,4 @@
-import { Action } from "./result_item";
+import { Action, ResultItem } from "./result_item";
 
 export enum IpcCommand {
   Query = "Query",
@@ -13,6 +13,51 @@ export enum IpcEvent {
   UpdateConfig = "UpdateConfig",
   UpdateSystemAccentColor = "UpdateSystemAccentColor",
   UpdateCustomCSS = "UpdateCustomCSS",
+  QueryResults = "QueryResults",
+  ReplaceResults = "ReplaceResults",
+  SetQuery = "SetQuery",
+  ShowMessage = "ShowMessage",
+  ActionStarted = "ActionStarted",
+  ActionProgress = "ActionProgress",
+  ActionFinished = "ActionFinished",
+  ActionFailed = "ActionFailed",
+  IndexingProgress = "IndexingProgress",
+  ConfigReloaded = "ConfigReloaded",
+}
+
+/** Payload of {@linkcode IpcEvent.QueryResults}. */
+export interface QueryResults {
+  /** Generation of the query these results belong to. */
+  generation: number;
+  /** All results of the query so far, sorted by score. */
+  results: ResultItem[];
+}
+
+/** Payload of {@linkcode IpcEvent.IndexingProgress}. */
+export interface IndexingProgress {
+  /** Name of the plugin that is indexing. */
+  plugin: string;
+  state: "indexing" | "done" | "failed";
+  /** Number of items scanned so far, while indexing. */
+  scanned?: number;
+  /** Number of items indexed, once done. */
+  items?: number;
+  /** Why indexing failed, the plugin keeps using its previous items. */
+  error?: string;
+}
+
+/**
+ * A problem found in the config file, returned by {@linkcode IpcCommand.Reload}
+ * and sent with {@linkcode IpcEvent.ConfigReloaded}.
+ */
+export interface ConfigDiagnostic {
+  severity: "Error" | "Warning";
+  message: string;
+  file: string | null;
+  /** 1-based line of the problem in `file`. */
+  line: number | null;
+  /** 1-based column of the problem in `file`. */
+  column: number | null;
 }
 
 export async function runAction(action: Action, itemId: string) {
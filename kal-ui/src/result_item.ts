@@ -28,6 +28,18 @@ export interface Action {
   description?: string;
 }
 
+/** A range of characters, `end` is exclusive. */
+export interface TextRange {
+  start: number;
+  end: number;
+}
+
+/** Characters of a {@linkcode ResultItem} that matched the query. */
+export interface Highlights {
+  primary_text: TextRange[];
+  secondary_text: TextRange[];
+}
+
 export interface ResultItem {
   id: string;
   icon: Icon;
@@ -35,4 +47,5 @@ export interface ResultItem {
   secondary_text: string;
   tooltip?: string;
   actions: Action[];
+  highlights?: Highlights;
 }
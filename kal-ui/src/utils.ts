@@ -1,4 +1,4 @@
-import { Icon, IconType } from "./result_item";
+import { Icon, IconType, TextRange } from "./result_item";
 
 export function makeIconHTML(icon: Icon): string {
   switch (icon.type) {
@@ -18,6 +18,37 @@ export function makeIconHTML(icon: Icon): string {
   }
 }
 
+/**
+ * Splits `text` into consecutive segments, marking the ones inside `ranges` as highlighted.
+ *
+ * Ranges count characters (code points) not UTF-16 units, so they are sliced using `Array.from`.
+ */
+export function highlightSegments(
+  text: string,
+  ranges: TextRange[] = [],
+): { text: string; highlighted: boolean }[] {
+  const chars = Array.from(text);
+  const segments = [];
+
+  let start = 0;
+  for (const range of ranges) {
+    const end = Math.min(range.end, chars.length);
+    if (range.start < start || range.start >= end) continue;
+
+    if (range.start > start) {
+      segments.push({ text: chars.slice(start, range.start).join(""), highlighted: false });
+    }
+    segments.push({ text: chars.slice(range.start, end).join(""), highlighted: true });
+    start = end;
+  }
+
+  if (start < chars.length) {
+    segments.push({ text: chars.slice(start).join(""), highlighted: false });
+  }
+
+  return segments;
+}
+
 export function isVScrollable<T extends Element>(el: T | null): boolean {
   return el ? el.scrollHeight > el.clientHeight : false;
 }
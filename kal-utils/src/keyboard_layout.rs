@@ -0,0 +1,101 @@
+/// Characters typed by the keys of a US QWERTY keyboard without modifiers,
+/// row by row from the top left key, every layout lists its keys in the same order.
+pub const ENGLISH_KEYS: [&str; 47] = [
+    "`", "1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "-", "=", //
+    "q", "w", "e", "r", "t", "y", "u", "i", "o", "p", "[", "]", "\\", //
+    "a", "s", "d", "f", "g", "h", "j", "k", "l", ";", "'", //
+    "z", "x", "c", "v", "b", "n", "m", ",", ".", "/",
+];
+
+/// Russian ЙЦУКЕН keys, see [`ENGLISH_KEYS`].
+pub const RUSSIAN_KEYS: [&str; 47] = [
+    "ё", "1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "-", "=", //
+    "й", "ц", "у", "к", "е", "н", "г", "ш", "щ", "з", "х", "ъ", "\\", //
+    "ф", "ы", "в", "а", "п", "р", "о", "л", "д", "ж", "э", //
+    "я", "ч", "с", "м", "и", "т", "ь", "б", "ю", ".",
+];
+
+/// Arabic (101) keys, see [`ENGLISH_KEYS`].
+pub const ARABIC_KEYS: [&str; 47] = [
+    "ذ", "1", "2", "3", "4", "5", "6", "7", "8", "9", "0", "-", "=", //
+    "ض", "ص", "ث", "ق", "ف", "غ", "ع", "ه", "خ", "ح", "ج", "د", "\\", //
+    "ش", "س", "ي", "ب", "ل", "ا", "ت", "ن", "م", "ك", "ط", //
+    "ئ", "ء", "ؤ", "ر", "لا", "ى", "ة", "و", "ز", "ظ",
+];
+
+/// Remaps `text` typed with the `from` layout active to what the same keys type
+/// in the `to` layout, for example `сщву` typed in Russian is `code` in English.
+///
+/// Characters that aren't in `from` are kept as is and the text is lowercased.
+/// Returns `None` if remapping doesn't change any letter or digit, since text
+/// like `code.exe` would otherwise be remapped to `code/exe` for nothing.
+pub fn remap_keyboard_layout(text: &str, from: &[&str], to: &[&str]) -> Option<String> {
+    let text = text.to_lowercase();
+
+    let mut remapped = String::with_capacity(text.len());
+    let mut rest = text.as_str();
+    let mut changed = false;
+
+    while let Some(c) = rest.chars().next() {
+        // some keys type more than one character, like the Arabic `لا`, prefer the longest
+        let key = from
+            .iter()
+            .zip(to)
+            .filter(|(key, _)| rest.starts_with(**key))
+            .max_by_key(|(key, _)| key.len());
+
+        match key {
+            Some((key, remapped_key)) => {
+                let is_alphanumeric = |key: &str| key.chars().any(char::is_alphanumeric);
+                changed |=
+                    key != remapped_key && (is_alphanumeric(key) || is_alphanumeric(remapped_key));
+
+                remapped.push_str(remapped_key);
+                rest = &rest[key.len()..];
+            }
+            None => {
+                remapped.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    changed.then_some(remapped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_remaps_between_layouts() {
+        let remap = remap_keyboard_layout;
+
+        assert_eq!(
+            remap("сщву", &RUSSIAN_KEYS, &ENGLISH_KEYS).as_deref(),
+            Some("code")
+        );
+        assert_eq!(
+            remap("Ghbdtn", &ENGLISH_KEYS, &RUSSIAN_KEYS).as_deref(),
+            Some("привет")
+        );
+        assert_eq!(
+            remap("ؤخيث", &ARABIC_KEYS, &ENGLISH_KEYS).as_deref(),
+            Some("code")
+        );
+        assert_eq!(
+            remap("لاشس", &ARABIC_KEYS, &ENGLISH_KEYS).as_deref(),
+            Some("bas")
+        );
+        assert_eq!(
+            remap("vs 2022", &ENGLISH_KEYS, &RUSSIAN_KEYS).as_deref(),
+            Some("мы 2022")
+        );
+        assert_eq!(remap("code", &RUSSIAN_KEYS, &ENGLISH_KEYS), None);
+        assert_eq!(remap("code.exe", &RUSSIAN_KEYS, &ENGLISH_KEYS), None);
+        assert_eq!(
+            remap("ghbdtn.", &ENGLISH_KEYS, &RUSSIAN_KEYS).as_deref(),
+            Some("приветю")
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod iterator;
+pub mod keyboard_layout;
 pub mod path;
 pub mod shell;
 #[cfg(windows)]
@@ -7,6 +8,7 @@ pub mod string;
 pub mod system_accent;
 
 pub use self::iterator::*;
+pub use self::keyboard_layout::*;
 pub use self::path::*;
 pub use self::shell::*;
 #[cfg(windows)]
@@ -47,6 +47,43 @@ where
     imp::execute_in_shell(shell, script, cwd, hidden, elevated)
 }
 
+/// Runs `script` through `shell` with the extra environment variables `envs`,
+/// waits for it to exit and returns its stdout.
+///
+/// Fails if the script exits with a non-zero status.
+/// The script is killed if the returned future is dropped before it exits.
+#[inline]
+pub async fn execute_in_shell_with_output<S, P>(
+    shell: Option<S>,
+    script: S,
+    cwd: Option<P>,
+    envs: &[(&str, &str)],
+) -> anyhow::Result<String>
+where
+    S: AsRef<str>,
+    P: AsRef<Path>,
+{
+    imp::execute_in_shell_with_output(shell, script, cwd, envs).await
+}
+
+/// Runs `app` with `args`, waits for it to exit and returns its stdout.
+///
+/// Fails if the app exits with a non-zero status.
+/// The app is killed if the returned future is dropped before it exits.
+#[inline]
+pub async fn execute_with_output<I, A, P>(
+    app: impl AsRef<std::ffi::OsStr>,
+    args: I,
+    cwd: Option<P>,
+) -> anyhow::Result<String>
+where
+    I: IntoIterator<Item = A>,
+    A: AsRef<std::ffi::OsStr>,
+    P: AsRef<Path>,
+{
+    imp::execute_with_output(app, args, cwd).await
+}
+
 #[cfg(windows)]
 mod imp {
     use windows::core::*;
@@ -215,6 +252,71 @@ mod imp {
         }
     }
 
+    pub async fn execute_in_shell_with_output<S, P>(
+        shell: Option<S>,
+        script: S,
+        cwd: Option<P>,
+        envs: &[(&str, &str)],
+    ) -> anyhow::Result<String>
+    where
+        S: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        use smol::process::windows::CommandExt;
+
+        let (shell, args) = {
+            let s = shell.as_ref().map(|s| s.as_ref());
+            let s = s.unwrap_or("powershell -Command");
+            s.split_once(' ').unwrap_or((s, ""))
+        };
+
+        let mut cmd = smol::process::Command::new(shell);
+        cmd.raw_arg(args).raw_arg(script.as_ref());
+        cmd.envs(envs.iter().copied());
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+
+        output(cmd).await
+    }
+
+    pub async fn execute_with_output<I, A, P>(
+        app: impl AsRef<std::ffi::OsStr>,
+        args: I,
+        cwd: Option<P>,
+    ) -> anyhow::Result<String>
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<std::ffi::OsStr>,
+        P: AsRef<Path>,
+    {
+        let mut cmd = smol::process::Command::new(app);
+        cmd.args(args);
+        if let Some(cwd) = cwd {
+            cmd.current_dir(cwd);
+        }
+
+        output(cmd).await
+    }
+
+    async fn output(mut cmd: smol::process::Command) -> anyhow::Result<String> {
+        use smol::process::windows::CommandExt;
+
+        const CREATE_NO_WINDOW: u32 = 0x08000000;
+        cmd.creation_flags(CREATE_NO_WINDOW);
+
+        // outdated queries drop the future, which shouldn't leave the process running
+        cmd.kill_on_drop(true);
+
+        let output = cmd.output().await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Process exited with {}: {}", output.status, stderr.trim());
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
     #[allow(non_snake_case)]
     mod ffi {
         use super::*;
@@ -284,4 +386,30 @@ mod imp {
     {
         unimplemented!()
     }
+
+    pub async fn execute_in_shell_with_output<S, P>(
+        shell: Option<S>,
+        script: S,
+        cwd: Option<P>,
+        envs: &[(&str, &str)],
+    ) -> anyhow::Result<String>
+    where
+        S: AsRef<str>,
+        P: AsRef<Path>,
+    {
+        unimplemented!()
+    }
+
+    pub async fn execute_with_output<I, A, P>(
+        app: impl AsRef<std::ffi::OsStr>,
+        args: I,
+        cwd: Option<P>,
+    ) -> anyhow::Result<String>
+    where
+        I: IntoIterator<Item = A>,
+        A: AsRef<std::ffi::OsStr>,
+        P: AsRef<Path>,
+    {
+        unimplemented!()
+    }
 }
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::{mpsc, Arc};
+use std::time::Duration;
 
 use global_hotkey::hotkey::HotKey;
 use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
@@ -20,7 +21,7 @@ use wry::WebContext;
 
 use crate::icon;
 use crate::ipc::IpcEvent;
-use crate::main_window::MainWindowState;
+use crate::main_window::{MainWindowMessage, MainWindowState};
 use crate::webview_window::WebViewWindow;
 
 #[derive(Debug)]
@@ -45,10 +46,16 @@ pub struct App {
 
     pub config: Config,
 
+    /// Directory where kal stores its data, like logs, icons and plugins.
+    pub data_dir: PathBuf,
+
     pub global_hotkey_manager: GlobalHotKeyManager,
 
     pub windows: HashMap<&'static str, WebViewWindow>,
 
+    /// Sends messages to the main window state, set once the main window is created.
+    pub main_window_sender: Option<smol::channel::Sender<MainWindowMessage>>,
+
     #[cfg(windows)]
     pub previously_foreground_hwnd: HWND,
 
@@ -61,6 +68,9 @@ pub struct App {
 }
 
 impl App {
+    /// How long to wait for plugins to shut down before exiting.
+    const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
     pub fn new(kal_data_dir: PathBuf, event_loop_proxy: EventLoopProxy) -> anyhow::Result<Self> {
         let (sender, receiver) = mpsc::channel();
 
@@ -129,8 +139,10 @@ impl App {
             sender,
             receiver,
             config,
+            data_dir: kal_data_dir,
             global_hotkey_manager,
             windows: HashMap::default(),
+            main_window_sender: None,
             #[cfg(windows)]
             previously_foreground_hwnd: HWND::default(),
             icon_service,
@@ -160,18 +172,58 @@ impl App {
         let main_window = self.main_window();
         main_window.window().set_visible(true);
         main_window.window().focus_window();
-        main_window.emit(IpcEvent::FocusInput, ())
+        main_window.emit(IpcEvent::FocusInput, ())?;
+
+        self.send_main_window_message(MainWindowMessage::Shown);
+
+        Ok(())
     }
 
     pub fn hide_main_window(&self, #[allow(unused)] restore_focus: bool) {
         self.main_window().window().set_visible(false);
 
+        self.send_main_window_message(MainWindowMessage::Hidden);
+
         #[cfg(windows)]
         if restore_focus {
             self.restore_prev_foreground_hwnd();
         }
     }
 
+    /// Sends `message` to the main window state, which handles it asynchronously.
+    fn send_main_window_message(&self, message: MainWindowMessage) {
+        let Some(sender) = &self.main_window_sender else {
+            return;
+        };
+
+        if let Err(e) = sender.try_send(message) {
+            tracing::error!("Failed to send main window message: {e}");
+        }
+    }
+
+    /// Lets plugins shut down, waiting for them up to [`Self::SHUTDOWN_TIMEOUT`].
+    fn shutdown_plugins(&self) {
+        let (done, finished) = smol::channel::bounded(1);
+        self.send_main_window_message(MainWindowMessage::Shutdown { done });
+
+        smol::block_on(async {
+            let finished = async {
+                // the channel is closed once plugins are shut down
+                let _ = finished.recv().await;
+                true
+            };
+
+            let timeout = async {
+                smol::Timer::after(Self::SHUTDOWN_TIMEOUT).await;
+                false
+            };
+
+            if !smol::future::or(finished, timeout).await {
+                tracing::warn!("Plugins took too long to shut down");
+            }
+        });
+    }
+
     #[cfg(windows)]
     fn listen_for_settings_change(&self, event_loop: &dyn ActiveEventLoop) {
         use windows::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
@@ -225,7 +277,7 @@ impl App {
                 let main_window = self.main_window().window();
                 // hide main window when it loses focus
                 if window_id == main_window.id() && !focus {
-                    main_window.set_visible(false);
+                    self.hide_main_window(false);
                 }
             }
 
@@ -296,6 +348,8 @@ impl ApplicationHandler for App {
 
     fn exiting(&mut self, _event_loop: &dyn ActiveEventLoop) {
         tracing::debug!("Eventloop Exited");
+
+        self.shutdown_plugins();
     }
 
     fn can_create_surfaces(&mut self, event_loop: &dyn ActiveEventLoop) {
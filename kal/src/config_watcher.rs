@@ -0,0 +1,138 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::Context;
+use kal_config::Config;
+use notify::{RecommendedWatcher, RecursiveMode};
+use notify_debouncer_mini::{DebounceEventResult, Debouncer};
+
+use crate::main_window::MainWindowMessage;
+
+/// Watches the config file and the custom CSS file, so changes
+/// take effect without reloading from the UI.
+pub struct ConfigWatcher {
+    sender: smol::channel::Sender<MainWindowMessage>,
+    debouncer: Option<Debouncer<RecommendedWatcher>>,
+}
+
+impl ConfigWatcher {
+    /// Editors usually save files in several steps, so changes are batched for this long.
+    const DEBOUNCE: Duration = Duration::from_millis(500);
+
+    /// Creates a watcher sending [`MainWindowMessage::ConfigChanged`]
+    /// and [`MainWindowMessage::CustomCSSChanged`] through `sender`.
+    pub fn new(sender: smol::channel::Sender<MainWindowMessage>) -> Self {
+        Self {
+            sender,
+            debouncer: None,
+        }
+    }
+
+    /// Watches the files of `config`, replacing the ones watched before
+    /// since the custom CSS file may have changed.
+    pub fn watch(&mut self, config: &Config) -> anyhow::Result<()> {
+        // dropping the debouncer stops watching
+        self.debouncer = None;
+
+        let config_file = WatchedFile::new(&Config::path()?)?;
+        let css_file = config
+            .appearance
+            .custom_css_file
+            .as_deref()
+            .map(WatchedFile::new)
+            .transpose()?;
+
+        let sender = self.sender.clone();
+        let config_file_ = config_file.clone();
+        let css_file_ = css_file.clone();
+        let mut debouncer = notify_debouncer_mini::new_debouncer(Self::DEBOUNCE, move |e| {
+            let Ok(events): DebounceEventResult = e else {
+                return;
+            };
+
+            let changed = |file: &WatchedFile| events.iter().any(|e| file.matches(&e.path));
+
+            let config_changed = changed(&config_file_);
+            let css_changed = css_file_.as_ref().is_some_and(changed);
+
+            // reloading the config also reloads the custom CSS
+            let message = if config_changed {
+                tracing::debug!("Config changed, reloading");
+                MainWindowMessage::ConfigChanged
+            } else if css_changed {
+                tracing::debug!("Custom CSS changed, reloading");
+                MainWindowMessage::CustomCSSChanged
+            } else {
+                return;
+            };
+
+            if let Err(e) = sender.try_send(message) {
+                tracing::error!("Failed to send config change: {e}");
+            }
+        })?;
+
+        // the parent directories are watched, since editors
+        // may replace the files instead of writing to them
+        let mut dirs = Vec::<&Path>::new();
+        for file in std::iter::once(&config_file).chain(css_file.as_ref()) {
+            if !dirs.contains(&file.dir.as_path()) {
+                debouncer
+                    .watcher()
+                    .watch(&file.dir, RecursiveMode::NonRecursive)?;
+                dirs.push(&file.dir);
+            }
+        }
+
+        self.debouncer.replace(debouncer);
+
+        Ok(())
+    }
+}
+
+/// A watched file, resolved to the directory it really is in, so events about it
+/// match regardless of symlinks or how its path is spelled, like a `\\?\` prefix.
+#[derive(Debug, Clone)]
+struct WatchedFile {
+    /// The canonical directory of the file, which is watched,
+    /// so the paths of its events start with it.
+    dir: PathBuf,
+    name: OsString,
+}
+
+impl WatchedFile {
+    fn new(path: &Path) -> anyhow::Result<Self> {
+        // the file itself may not exist yet, like a custom CSS file about to be created
+        let path = match dunce::canonicalize(path) {
+            Ok(path) => path,
+            Err(_) => {
+                let path = std::path::absolute(path)?;
+                let dir = path.parent().context("File has no parent directory")?;
+                dunce::canonicalize(dir)?.join(path.file_name().unwrap_or_default())
+            }
+        };
+
+        let dir = path.parent().context("File has no parent directory")?;
+        let name = path.file_name().context("Path has no file name")?;
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            name: name.to_owned(),
+        })
+    }
+
+    fn matches(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+
+        // file names are case insensitive on Windows
+        let same_name = if cfg!(windows) {
+            name.eq_ignore_ascii_case(&self.name)
+        } else {
+            name == self.name
+        };
+
+        same_name && path.parent() == Some(&self.dir)
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Results the user launched, persisted to a JSON file
+/// and used to boost results that are used often and recently.
+#[derive(Debug, Default)]
+pub struct History {
+    /// File the history is saved to, `None` keeps it in memory only.
+    path: Option<PathBuf>,
+    records: Mutex<Vec<Record>>,
+    /// Held while saving, so concurrent saves don't interleave their writes.
+    save_lock: smol::lock::Mutex<()>,
+}
+
+/// A single launch of a result.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    plugin: String,
+    item: String,
+    /// The query the result was launched from.
+    query: String,
+    /// Seconds since the unix epoch.
+    timestamp: u64,
+}
+
+impl History {
+    /// Max number of records kept, older ones are dropped.
+    const MAX_RECORDS: usize = 2000;
+
+    /// Loads the history saved at `path`, starting from an empty history if it can't be read.
+    pub fn load(path: PathBuf) -> Self {
+        let records = std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| serde_json::from_str(&json).map_err(Into::into))
+            .inspect_err(|e| {
+                if path.exists() {
+                    tracing::error!("Failed to load history from `{}`: {e}", path.display());
+                }
+            })
+            .unwrap_or_default();
+
+        Self {
+            path: Some(path),
+            records: Mutex::new(records),
+            save_lock: Default::default(),
+        }
+    }
+
+    /// Records a launch of `item` produced by `plugin` and saves the history.
+    pub async fn record(&self, plugin: &str, item: &str, query: &str) -> anyhow::Result<()> {
+        {
+            let mut records = self.records.lock().unwrap();
+
+            records.push(Record {
+                plugin: plugin.to_owned(),
+                item: item.to_owned(),
+                query: normalize_query(query),
+                timestamp: now(),
+            });
+
+            if records.len() > Self::MAX_RECORDS {
+                let excess = records.len() - Self::MAX_RECORDS;
+                records.drain(..excess);
+            }
+        }
+
+        self.save().await
+    }
+
+    /// Saves the history to a temporary file first and swaps it in once written,
+    /// so a crash while saving doesn't truncate the saved history.
+    async fn save(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let _saving = self.save_lock.lock().await;
+
+        // serialized while holding the save lock, so the latest records are saved last
+        let json = serde_json::to_string(&*self.records.lock().unwrap())?;
+
+        let temp_path = path.with_extension("json.tmp");
+        smol::fs::write(&temp_path, json).await?;
+        smol::fs::rename(&temp_path, path).await?;
+
+        Ok(())
+    }
+
+    /// Computes how often and how recently each result was launched for `query`,
+    /// a launch counts half as much every `half_life` days.
+    pub fn frecency(&self, query: &str, weight: u16, half_life: f64) -> Frecency {
+        let mut scores: HashMap<String, HashMap<String, f64>> = HashMap::new();
+
+        if weight == 0 {
+            return Frecency { weight, scores };
+        }
+
+        let query = normalize_query(query);
+        let half_life = half_life.max(f64::EPSILON) * 24.0 * 60.0 * 60.0;
+        let now = now();
+
+        for record in self.records.lock().unwrap().iter() {
+            let age = now.saturating_sub(record.timestamp) as f64;
+            let mut score = 0.5_f64.powf(age / half_life);
+
+            // launches from a similar query count more,
+            // so typing `c` learns which `c` the user usually means
+            if !query.is_empty() && record.query.starts_with(&query) {
+                score *= 2.0;
+            }
+
+            *scores
+                .entry(record.plugin.clone())
+                .or_default()
+                .entry(record.item.clone())
+                .or_default() += score;
+        }
+
+        Frecency { weight, scores }
+    }
+}
+
+/// Frecency of launched results for a single query, see [`History::frecency`].
+#[derive(Debug, Default)]
+pub struct Frecency {
+    weight: u16,
+    scores: HashMap<String, HashMap<String, f64>>,
+}
+
+impl Frecency {
+    /// How much to add to the match score of `item` produced by `plugin`.
+    ///
+    /// Grows logarithmically, so a handful of launches makes a difference
+    /// but a result launched all day long doesn't bury every other match.
+    pub fn boost(&self, plugin: &str, item: &str) -> u16 {
+        let Some(score) = self.scores.get(plugin).and_then(|s| s.get(item)) else {
+            return 0;
+        };
+
+        (self.weight as f64 * score.ln_1p()) as u16
+    }
+}
+
+fn normalize_query(query: &str) -> String {
+    query.trim().to_lowercase()
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
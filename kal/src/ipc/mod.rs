@@ -30,6 +30,27 @@ pub enum IpcEvent {
     UpdateConfig,
     UpdateSystemAccentColor,
     UpdateCustomCSS,
+    /// A batch of results for a query, merged with the previous batches.
+    QueryResults,
+    /// Results replaced by an action, see [`kal_plugin::ActionOutcome::ReplaceResults`].
+    ReplaceResults,
+    /// Search text set by an action, see [`kal_plugin::ActionOutcome::SetQuery`].
+    SetQuery,
+    /// A transient message from an action, see [`kal_plugin::ActionOutcome::ShowMessage`].
+    ShowMessage,
+    /// An async action started running, with the action id as payload.
+    ActionStarted,
+    /// A progress message from a running async action.
+    ActionProgress,
+    /// An async action finished successfully.
+    ActionFinished,
+    /// An action failed, with the error message as payload.
+    ActionFailed,
+    /// Progress of a plugin indexing in the background, see [`kal_plugin::IndexingStatus`].
+    IndexingProgress,
+    /// The config was reloaded after its file changed,
+    /// with the problems found in it as payload.
+    ConfigReloaded,
 }
 
 const EMIT_TEMPLATE: &str = r#"(function(){{
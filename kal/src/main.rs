@@ -13,12 +13,16 @@ use tracing_subscriber::EnvFilter;
 use winit::event_loop::{ControlFlow, EventLoop};
 
 mod app;
+mod config_watcher;
 #[cfg(not(debug_assertions))]
 mod embedded_assets;
+mod history;
 mod icon;
 mod ipc;
 mod main_window;
+mod plugin_host;
 mod plugin_manager;
+mod plugin_stats;
 mod webview_window;
 
 fn error_dialog<T: Display>(error: T) {
@@ -63,7 +67,25 @@ pub fn run(data_dir: PathBuf) -> anyhow::Result<()> {
     event_loop.run_app(&mut app).map_err(Into::into)
 }
 
+/// Writes the config JSON schema to `path`, including the options of each builtin plugin.
+fn write_config_schema(path: &str) -> anyhow::Result<()> {
+    let mut schema = kal_config::ConfigSchema::new();
+    crate::plugin_manager::PluginManager::builtin_config_schemas(&mut schema);
+
+    let schema = serde_json::to_string(&schema.generate())?;
+    std::fs::write(path, schema).with_context(|| format!("Failed to write schema to {path}"))
+}
+
 fn main() -> anyhow::Result<()> {
+    // `kal --config-schema kal-config/schema.json` updates the schema shipped with the repo
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("--config-schema") {
+        let path = args
+            .next()
+            .context("Missing path after `--config-schema`")?;
+        return write_config_schema(&path);
+    }
+
     let data_dir = dirs::data_dir()
         .context("Failed to get $data_dir path")?
         .join("kal");
@@ -1,8 +1,12 @@
-use std::sync::{mpsc, Arc};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
 
 use global_hotkey::hotkey::HotKey;
-use kal_config::Config;
-use kal_plugin::ResultItem;
+use kal_config::{Config, ConfigDiagnostic};
+use kal_plugin::{
+    Action, ActionOutcome, ActionProgress, CancellationToken, PluginEvent, PluginEvents, ResultItem,
+};
 use serialize_to_javascript::{Options as JsSerializeOptions, Template as JsTemplate};
 use smol::lock::RwLock;
 use winit::dpi::LogicalSize;
@@ -10,9 +14,10 @@ use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
 use wry::http::Request;
 
 use crate::app::{App, AppMessage};
+use crate::config_watcher::ConfigWatcher;
 use crate::icon;
 use crate::ipc::{response, AsyncIpcMessage, IpcCommand, IpcEvent, IpcResult};
-use crate::plugin_manager::PluginManager;
+use crate::plugin_manager::{PluginManager, QueryResult};
 use crate::webview_window::{WebViewWindow, WebViewWindowBuilder};
 
 const INIT_TEMPLATE: &str = r#"(function () {
@@ -52,10 +57,13 @@ impl App {
 
         let async_ipc_sender = MainWindowState::spawn(
             self.config.clone(),
+            self.data_dir.clone(),
             self.sender.clone(),
             self.event_loop_proxy.clone(),
         );
 
+        self.main_window_sender = Some(async_ipc_sender.clone());
+
         let icon_service = self.icon_service.clone();
 
         let builder = WebViewWindowBuilder::new(&mut self.web_context)
@@ -91,11 +99,27 @@ impl App {
 }
 
 #[derive(Debug)]
-enum MainWindowMessage {
+pub enum MainWindowMessage {
     Ipc {
         request: wry::http::Request<Vec<u8>>,
         tx: smol::channel::Sender<IpcResult>,
     },
+    /// The main window was shown, see [`kal_plugin::Plugin::on_show`].
+    Shown,
+    /// The main window was hidden, see [`kal_plugin::Plugin::on_hide`].
+    Hidden,
+    /// kal is exiting, `done` is closed once plugins are shut down,
+    /// see [`kal_plugin::Plugin::on_shutdown`].
+    Shutdown { done: smol::channel::Sender<()> },
+    /// An event `plugin` sent through its [`kal_plugin::PluginHandle`].
+    Plugin {
+        plugin: Arc<str>,
+        event: PluginEvent,
+    },
+    /// The config file changed, see [`ConfigWatcher`].
+    ConfigChanged,
+    /// The custom CSS file changed, see [`ConfigWatcher`].
+    CustomCSSChanged,
 }
 
 impl From<AsyncIpcMessage> for MainWindowMessage {
@@ -113,7 +137,16 @@ pub struct MainWindowState {
 
     config: RwLock<Config>,
     plugin_manager: RwLock<PluginManager>,
-    results: RwLock<Vec<ResultItem>>,
+    results: RwLock<Vec<QueryResult>>,
+    /// Generation of the latest query, set by the UI,
+    /// results of older queries are dropped.
+    generation: AtomicU64,
+    /// Cancelled when a newer query starts, so plugins stop working on the latest one.
+    cancellation: Mutex<CancellationToken>,
+    /// The latest query, recorded along with launched results.
+    query: Mutex<String>,
+    /// Reloads the config when its file changes.
+    config_watcher: Mutex<ConfigWatcher>,
 }
 
 impl MainWindowState {
@@ -126,12 +159,20 @@ impl MainWindowState {
 
     async fn new(
         config: Config,
+        data_dir: PathBuf,
+        sender: smol::channel::Sender<MainWindowMessage>,
         main_thread_sender: mpsc::Sender<AppMessage>,
         event_loop_proxy: EventLoopProxy,
     ) -> Self {
         let max_results = config.general.max_results;
 
-        let mut plugin_manager = PluginManager::all(&config);
+        let mut config_watcher = ConfigWatcher::new(sender.clone());
+        if let Err(e) = config_watcher.watch(&config) {
+            tracing::error!("Failed to watch config: {e}");
+        }
+
+        let events = Self::plugin_events(sender);
+        let mut plugin_manager = PluginManager::all(&config, &data_dir, events);
         plugin_manager.reload(&config).await;
 
         Self {
@@ -140,17 +181,28 @@ impl MainWindowState {
             config: RwLock::new(config),
             plugin_manager: RwLock::new(plugin_manager),
             results: RwLock::new(Vec::with_capacity(max_results)),
+            generation: AtomicU64::new(0),
+            cancellation: Mutex::new(CancellationToken::new()),
+            query: Mutex::new(String::new()),
+            config_watcher: Mutex::new(config_watcher),
         }
     }
 
     fn spawn(
         config: Config,
+        data_dir: PathBuf,
         main_thread_sender: mpsc::Sender<AppMessage>,
         event_loop_proxy: EventLoopProxy,
     ) -> smol::channel::Sender<MainWindowMessage> {
         let (sender, receiver) = smol::channel::unbounded();
 
-        let state = MainWindowState::new(config, main_thread_sender, event_loop_proxy);
+        let state = MainWindowState::new(
+            config,
+            data_dir,
+            sender.clone(),
+            main_thread_sender,
+            event_loop_proxy,
+        );
 
         smol::spawn(async move {
             let state = Arc::new(state.await);
@@ -170,6 +222,59 @@ impl MainWindowState {
                             })
                             .detach();
                         }
+
+                        MainWindowMessage::Shown => {
+                            let state = state.clone();
+                            smol::spawn(async move {
+                                state.plugin_manager.read().await.on_show().await;
+                            })
+                            .detach();
+                        }
+
+                        MainWindowMessage::Hidden => {
+                            let state = state.clone();
+                            smol::spawn(async move {
+                                state.plugin_manager.read().await.on_hide().await;
+                            })
+                            .detach();
+                        }
+
+                        MainWindowMessage::Shutdown { done } => {
+                            state.plugin_manager.read().await.on_shutdown().await;
+                            drop(done);
+                        }
+
+                        MainWindowMessage::Plugin { plugin, event } => {
+                            let state = state.clone();
+                            smol::spawn(async move {
+                                if let Err(e) = state.plugin_event(plugin, event).await {
+                                    tracing::error!("Failed to handle plugin event: {e}");
+                                }
+                            })
+                            .detach();
+                        }
+
+                        MainWindowMessage::ConfigChanged => {
+                            let state = state.clone();
+                            smol::spawn(async move {
+                                if let Err(e) = state.config_changed().await {
+                                    tracing::error!("Failed to reload config: {e}");
+                                }
+                            })
+                            .detach();
+                        }
+
+                        MainWindowMessage::CustomCSSChanged => {
+                            let state = state.clone();
+                            smol::spawn(async move {
+                                let config = state.config.read().await;
+                                if let Err(e) = state.update_custom_css(&config).await {
+                                    tracing::error!("Failed to reload custom CSS: {e}");
+                                }
+                                state.wake_event_loop();
+                            })
+                            .detach();
+                        }
                     }
                 }
             }
@@ -213,6 +318,327 @@ impl MainWindowState {
         self.send_event(AppMessage::RequestSufaceSize(size.into()))
     }
 
+    /// Starts a new generation of results for `query`, dropping results of older queries
+    /// and cancelling their in-flight plugin work.
+    ///
+    /// Returns the cancellation token of the new generation, or `None` if
+    /// a newer query already started, since IPC requests can be handled out of order.
+    async fn reset_results(&self, generation: u64, query: &str) -> Option<CancellationToken> {
+        let mut results = self.results.write().await;
+
+        if generation < self.generation.load(Ordering::SeqCst) {
+            return None;
+        }
+
+        self.generation.store(generation, Ordering::SeqCst);
+        results.clear();
+        *self.query.lock().unwrap() = query.to_owned();
+
+        let token = CancellationToken::new();
+        let old = std::mem::replace(&mut *self.cancellation.lock().unwrap(), token.clone());
+        old.cancel();
+
+        Some(token)
+    }
+
+    /// The generation, query and cancellation token of the latest query,
+    /// read together since [`Self::reset_results`] changes them together.
+    async fn current_query(&self) -> (u64, String, CancellationToken) {
+        let _results = self.results.read().await;
+
+        let generation = self.generation.load(Ordering::SeqCst);
+        let query = self.query.lock().unwrap().clone();
+        let cancellation = self.cancellation.lock().unwrap().clone();
+
+        (generation, query, cancellation)
+    }
+
+    /// Merges a batch of results into the current results and pushes them to the UI,
+    /// unless a newer query has started since.
+    async fn merge_results(&self, generation: u64, batch: Vec<QueryResult>) -> anyhow::Result<()> {
+        self.update_results(generation, batch, |_| true).await
+    }
+
+    /// Same as [`Self::merge_results`] but drops the previous results of `plugin` first.
+    async fn replace_plugin_results(
+        &self,
+        generation: u64,
+        plugin: &str,
+        batch: Vec<QueryResult>,
+    ) -> anyhow::Result<()> {
+        self.update_results(generation, batch, |r| *r.plugin != *plugin)
+            .await
+    }
+
+    /// Keeps the current results `keep` returns `true` for, merges a batch of results
+    /// into them and pushes them to the UI, unless a newer query has started since.
+    async fn update_results(
+        &self,
+        generation: u64,
+        mut batch: Vec<QueryResult>,
+        keep: impl FnMut(&QueryResult) -> bool,
+    ) -> anyhow::Result<()> {
+        let mut results = self.results.write().await;
+
+        // the query is also cancelled when an action replaces its results
+        if self.generation.load(Ordering::SeqCst) != generation
+            || self.cancellation.lock().unwrap().is_cancelled()
+        {
+            return Ok(());
+        }
+
+        results.retain(keep);
+
+        batch.iter_mut().for_each(|r| r.item.sort_actions());
+        results.extend(batch);
+
+        // sort results by scores in descending order
+        results.sort_by(|a, b| b.item.score.cmp(&a.item.score));
+
+        let config = self.config.read().await;
+
+        let min = std::cmp::min(config.general.max_results, results.len());
+        let final_results = &results[..min];
+
+        let payload = serde_json::json!({ "generation": generation, "results": final_results });
+        self.batch_event(AppMessage::MainWindowEmit(IpcEvent::QueryResults, payload))?;
+
+        self.resize_main_window_for_items(&config, min)
+    }
+
+    /// Replaces the current results with follow-up items from an action of `plugin`,
+    /// cancelling the current query so its remaining batches are dropped.
+    async fn replace_results(
+        &self,
+        plugin: Arc<str>,
+        items: Vec<ResultItem>,
+    ) -> anyhow::Result<()> {
+        let mut results = self.results.write().await;
+
+        self.cancellation.lock().unwrap().cancel();
+        *results = items
+            .into_iter()
+            .map(|mut item| {
+                item.sort_actions();
+                let plugin = plugin.clone();
+                QueryResult { plugin, item }
+            })
+            .collect();
+
+        let config = self.config.read().await;
+
+        let min = std::cmp::min(config.general.max_results, results.len());
+        let payload = serde_json::json!(&results[..min]);
+        let event = AppMessage::MainWindowEmit(IpcEvent::ReplaceResults, payload);
+        self.batch_event(event)?;
+
+        self.resize_main_window_for_items(&config, min)
+    }
+
+    /// Finds the action with `action_id` of the result with `item_id`
+    /// and calls `f` with both while holding the results lock.
+    async fn with_action<T>(
+        &self,
+        item_id: &str,
+        action_id: &str,
+        f: impl FnOnce(&QueryResult, &Action) -> T,
+    ) -> anyhow::Result<T> {
+        let results = self.results.read().await;
+
+        let Some(result) = results.iter().find(|r| r.item.id == item_id) else {
+            anyhow::bail!("Couldn't find result item with this id: {item_id}");
+        };
+
+        let Some(action) = result.item.actions.iter().find(|a| a.id == action_id) else {
+            anyhow::bail!("Couldn't find secondary action: {action_id}");
+        };
+
+        Ok(f(result, action))
+    }
+
+    /// Forwards the events plugins send outside of queries
+    /// to the main window state, see [`Self::plugin_event`].
+    fn plugin_events(sender: smol::channel::Sender<MainWindowMessage>) -> PluginEvents {
+        PluginEvents::new(move |plugin, event| {
+            let plugin = plugin.into();
+            if let Err(e) = sender.try_send(MainWindowMessage::Plugin { plugin, event }) {
+                tracing::error!("Failed to send plugin event: {e}");
+            }
+        })
+    }
+
+    /// Handles an event `plugin` sent outside of queries.
+    async fn plugin_event(&self, plugin: Arc<str>, event: PluginEvent) -> anyhow::Result<()> {
+        match event {
+            PluginEvent::Indexing(status) => {
+                let plugin_manager = self.plugin_manager.read().await;
+                plugin_manager.record_indexing(&plugin, &status);
+                drop(plugin_manager);
+
+                let mut payload = serde_json::to_value(status)?;
+                payload["plugin"] = plugin.as_ref().into();
+                self.send_event(AppMessage::MainWindowEmit(
+                    IpcEvent::IndexingProgress,
+                    payload,
+                ))
+            }
+
+            PluginEvent::Requery => self.requery_plugin(plugin).await,
+
+            PluginEvent::PushResults(items) => {
+                let (generation, query, _) = self.current_query().await;
+
+                let plugin_manager = self.plugin_manager.read().await;
+                let queried = plugin_manager.is_queried(&plugin, &query);
+                drop(plugin_manager);
+
+                // pushed results only belong with the results of queries the plugin is part of
+                if query.is_empty() || !queried {
+                    return Ok(());
+                }
+
+                let batch = items
+                    .into_iter()
+                    .map(|item| {
+                        let plugin = plugin.clone();
+                        QueryResult { plugin, item }
+                    })
+                    .collect();
+
+                self.replace_plugin_results(generation, &plugin, batch)
+                    .await
+            }
+        }
+    }
+
+    /// Queries `plugin` again for the current query and replaces its results,
+    /// unless a newer query starts meanwhile.
+    async fn requery_plugin(&self, plugin: Arc<str>) -> anyhow::Result<()> {
+        let (generation, query, cancellation) = self.current_query().await;
+        if query.is_empty() {
+            return Ok(());
+        }
+
+        // the results are replaced all at once, so the plugin's results don't flicker
+        let batches = Mutex::new(Vec::new());
+        let on_batch = |batch: Vec<QueryResult>| batches.lock().unwrap().extend(batch);
+
+        let plugin_manager = self.plugin_manager.read().await;
+        plugin_manager
+            .requery(&plugin, &query, &cancellation, &on_batch)
+            .await;
+        drop(plugin_manager);
+
+        let batch = batches.into_inner().unwrap();
+        self.replace_plugin_results(generation, &plugin, batch)
+            .await
+    }
+
+    /// Creates an [`ActionProgress`] that forwards progress messages to the main window.
+    fn action_progress(&self) -> ActionProgress {
+        let sender = self.main_thread_sender.clone();
+        let event_loop_proxy = self.event_loop_proxy.clone();
+
+        ActionProgress::new(move |message| {
+            let event = AppMessage::MainWindowEmit(IpcEvent::ActionProgress, message.into());
+            if sender.send(event).is_ok() {
+                event_loop_proxy.wake_up();
+            }
+        })
+    }
+
+    /// Applies what an action of an item produced by `plugin` asked for after it finished running.
+    async fn apply_action_outcome(
+        &self,
+        plugin: Arc<str>,
+        outcome: ActionOutcome,
+    ) -> anyhow::Result<()> {
+        match outcome {
+            ActionOutcome::Hide => self.send_event(AppMessage::HideMainWindow(false)),
+            ActionOutcome::KeepOpen => Ok(()),
+            ActionOutcome::SetQuery(query) => {
+                let event = AppMessage::MainWindowEmit(IpcEvent::SetQuery, query.into());
+                self.send_event(event)
+            }
+            ActionOutcome::ReplaceResults(items) => self.replace_results(plugin, items).await,
+            ActionOutcome::ShowMessage(message) => {
+                let event = AppMessage::MainWindowEmit(IpcEvent::ShowMessage, message.into());
+                self.send_event(event)
+            }
+        }
+    }
+
+    /// Reloads the config and plugins, and pushes the new config to the UI.
+    ///
+    /// Returns the problems found in the config, which replace the current results.
+    async fn reload(&self) -> anyhow::Result<Vec<ConfigDiagnostic>> {
+        let mut config = self.config.write().await;
+
+        let old_hotkey = config.general.hotkey.clone();
+
+        *config = Config::load_with_fallback();
+
+        // the custom CSS file may have changed
+        if let Err(e) = self.config_watcher.lock().unwrap().watch(&config) {
+            tracing::error!("Failed to watch config: {e}");
+        }
+
+        let mut plugin_manager = self.plugin_manager.write().await;
+        plugin_manager.reload(&config).await;
+
+        let diagnostics = plugin_manager.config_diagnostics.clone();
+        let diagnostics_items = plugin_manager.config_diagnostics_items();
+        drop(plugin_manager);
+
+        let old_hotkey = HotKey::try_from(old_hotkey.as_str())?;
+        let new_hotkey = HotKey::try_from(config.general.hotkey.as_str())?;
+        if old_hotkey != new_hotkey {
+            self.batch_event(AppMessage::ReRegisterHotKey(old_hotkey, new_hotkey))?;
+        }
+
+        let json_config = serde_json::to_value(&*config)?;
+        let event = AppMessage::MainWindowEmit(IpcEvent::UpdateConfig, json_config);
+        self.batch_event(event)?;
+
+        self.update_custom_css(&config).await?;
+        self.wake_event_loop();
+
+        // problems in the config are shown in place of the results,
+        // the UI only queries again if there are none
+        drop(config);
+        if !diagnostics.is_empty() {
+            self.replace_results("kal".into(), diagnostics_items)
+                .await?;
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Reloads after the config file changed, letting the UI know
+    /// so it can query again, see [`Self::reload`].
+    async fn config_changed(&self) -> anyhow::Result<()> {
+        let diagnostics = self.reload().await?;
+        let event = AppMessage::MainWindowEmit(
+            IpcEvent::ConfigReloaded,
+            serde_json::to_value(diagnostics)?,
+        );
+        self.send_event(event)
+    }
+
+    /// Reads the custom CSS file of `config` and pushes it to the UI.
+    async fn update_custom_css(&self, config: &Config) -> anyhow::Result<()> {
+        let custom_css = match config.appearance.custom_css_file.as_ref() {
+            Some(path) => smol::fs::read_to_string(path)
+                .await
+                .map(serde_json::Value::String)
+                .unwrap_or(serde_json::Value::Null),
+
+            None => serde_json::Value::Null,
+        };
+        let event = AppMessage::MainWindowEmit(IpcEvent::UpdateCustomCSS, custom_css);
+        self.batch_event(event)
+    }
+
     async fn ipc_handler(&self, request: Request<Vec<u8>>) -> IpcResult {
         let span = tracing::debug_span!("ipc::handle::request", ?request);
         let _enter = span.enter();
@@ -223,29 +649,58 @@ impl MainWindowState {
 
         match ipc_command {
             IpcCommand::Query => {
-                let body = request.body();
-                let query = std::str::from_utf8(body)?;
+                let payload = request.body();
 
-                // it is fine to block here since only one query can be processed at a time
-                let mut plugins_store = self.plugin_manager.write().await;
+                let Some((generation, query)) = std::str::from_utf8(payload)?.split_once('#')
+                else {
+                    anyhow::bail!("Invalid payload for command `{ipc_command}`: {payload:?}");
+                };
 
-                let results = plugins_store.query(query).await?;
+                let generation = generation.parse()?;
 
-                let config = self.config.read().await;
+                let Some(cancellation) = self.reset_results(generation, query).await else {
+                    return response::empty();
+                };
+
+                let (tx, rx) = smol::channel::unbounded();
+
+                let plugins_store = self.plugin_manager.read().await;
+                let query = async move {
+                    let on_batch = |batch: Vec<QueryResult>| {
+                        let _ = tx.try_send(batch);
+                    };
 
-                let min = std::cmp::min(config.general.max_results, results.len());
-                let final_results = &results[..min];
+                    // `tx` is dropped once all plugins finish or get cancelled,
+                    // which closes the channel
+                    plugins_store.query(query, &cancellation, &on_batch).await;
+                };
 
-                let json = response::json(&final_results);
+                // results are pushed to the UI as they arrive
+                // instead of being returned in the response
+                let merge = async {
+                    while let Ok(batch) = rx.recv().await {
+                        self.merge_results(generation, batch).await?;
+                    }
 
-                self.resize_main_window_for_items(&config, min)?;
+                    anyhow::Ok(())
+                };
 
-                *self.results.write().await = results;
+                let ((), merged) = smol::future::zip(query, merge).await;
+                merged?;
 
-                return json;
+                // make sure the UI is in sync, even if no plugin returned any results
+                self.merge_results(generation, Vec::new()).await?;
             }
 
             IpcCommand::ClearResults => {
+                let payload = request.body();
+                let generation = std::str::from_utf8(payload)?.parse()?;
+
+                // cancels any queries still in-flight and drops their batches
+                if self.reset_results(generation, "").await.is_none() {
+                    return response::empty();
+                }
+
                 let config = self.config.read().await;
                 self.resize_main_window_for_items(&config, 0)?
             }
@@ -257,57 +712,134 @@ impl MainWindowState {
                     anyhow::bail!("Invalid payload for command `{ipc_command}`: {payload:?}");
                 };
 
-                let results = self.results.read().await;
+                let confirmation = self
+                    .with_action(id, action, |_, action| action.confirmation.clone())
+                    .await?;
 
-                let Some(item) = results.iter().find(|r| r.id == id) else {
-                    anyhow::bail!("Couldn't find result item with this id: {id}");
-                };
+                // the results lock isn't held while waiting for the user,
+                // so the action is looked up again once confirmed
+                if let Some(confirmation) = confirmation {
+                    if !smol::unblock(move || confirm_dialog(&confirmation)).await {
+                        return response::empty();
+                    }
+                }
 
-                let Some(action) = item.actions.iter().find(|a| a.id == action) else {
-                    anyhow::bail!("Couldn't find secondary action: {action}");
-                };
+                // dispatched actions are started by their plugin, the plugins
+                // are only locked until the action started, like the results
+                let plugin_manager = self.plugin_manager.read().await;
+                let (plugin, is_async, future) = self
+                    .with_action(id, action, |result, action| {
+                        let plugin = result.plugin.clone();
+                        let progress = self.action_progress();
+
+                        let future = if action.is_dispatched() {
+                            let payload = result.item.payload.clone();
+                            plugin_manager.run_action(&plugin, &action.id, payload, progress)
+                        } else {
+                            action.run(&result.item, progress)
+                        };
+
+                        (plugin, action.is_async(), future)
+                    })
+                    .await?;
+
+                // the launch is recorded once the action succeeded,
+                // without holding up reloads while the history is saved
+                let history = plugin_manager.history.clone();
+                let query = self.query.lock().unwrap().clone();
+
+                drop(plugin_manager);
+
+                if is_async {
+                    let event = AppMessage::MainWindowEmit(IpcEvent::ActionStarted, action.into());
+                    self.send_event(event)?;
+                }
 
-                action.run(item)?;
+                // the results lock is released here, so queries
+                // aren't held up while an async action is running
+                match future.await {
+                    Ok(outcome) => {
+                        if let Err(e) = history.record(&plugin, id, &query).await {
+                            tracing::error!("Failed to save history: {e}");
+                        }
 
-                self.send_event(AppMessage::HideMainWindow(false))?;
+                        if is_async {
+                            let event = AppMessage::MainWindowEmit(
+                                IpcEvent::ActionFinished,
+                                serde_json::Value::Null,
+                            );
+                            self.batch_event(event)?;
+                        }
+
+                        self.apply_action_outcome(plugin, outcome).await?
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to run action `{action}` for `{id}`: {e}");
+
+                        let event = AppMessage::MainWindowEmit(
+                            IpcEvent::ActionFailed,
+                            e.to_string().into(),
+                        );
+                        self.send_event(event)?;
+                    }
+                }
             }
 
             IpcCommand::Reload => {
-                let mut config = self.config.write().await;
+                let diagnostics = self.reload().await?;
+                return response::json(&diagnostics);
+            }
 
-                let old_hotkey = config.general.hotkey.clone();
+            IpcCommand::HideMainWindow => self.send_event(AppMessage::HideMainWindow(false))?,
+        }
 
-                *config = Config::load_with_fallback();
+        response::empty()
+    }
+}
 
-                let mut plugin_manager = self.plugin_manager.write().await;
-                plugin_manager.reload(&config).await;
+/// Shows a blocking confirmation dialog on top of the foreground window, usually the main window.
+fn confirm_dialog(description: &str) -> bool {
+    let dialog = rfd::MessageDialog::new()
+        .set_title("Please confirm")
+        .set_description(description)
+        .set_level(rfd::MessageLevel::Warning)
+        .set_buttons(rfd::MessageButtons::YesNo);
 
-                let old_hotkey = HotKey::try_from(old_hotkey.as_str())?;
-                let new_hotkey = HotKey::try_from(config.general.hotkey.as_str())?;
-                if old_hotkey != new_hotkey {
-                    self.batch_event(AppMessage::ReRegisterHotKey(old_hotkey, new_hotkey))?;
-                }
+    #[cfg(windows)]
+    let dialog = dialog.set_parent(&foreground_window::ForegroundWindow::get());
 
-                let json_config = serde_json::to_value(&*config)?;
-                let event = AppMessage::MainWindowEmit(IpcEvent::UpdateConfig, json_config);
-                self.batch_event(event)?;
+    dialog.show() == rfd::MessageDialogResult::Yes
+}
 
-                let custom_css = match config.appearance.custom_css_file.as_ref() {
-                    Some(path) => smol::fs::read_to_string(path)
-                        .await
-                        .map(serde_json::Value::String)
-                        .unwrap_or(serde_json::Value::Null),
+#[cfg(windows)]
+mod foreground_window {
+    use std::num::NonZeroIsize;
 
-                    None => serde_json::Value::Null,
-                };
-                let event = AppMessage::MainWindowEmit(IpcEvent::UpdateCustomCSS, custom_css);
-                self.batch_event(event)?;
-                self.wake_event_loop();
-            }
+    use raw_window_handle::*;
+    use windows::Win32::Foundation::HWND;
+    use windows::Win32::UI::WindowsAndMessaging::GetForegroundWindow;
 
-            IpcCommand::HideMainWindow => self.send_event(AppMessage::HideMainWindow(false))?,
+    /// The foreground window as a parent for dialogs,
+    /// so they don't show up behind the main window.
+    pub struct ForegroundWindow(HWND);
+
+    impl ForegroundWindow {
+        pub fn get() -> Self {
+            Self(unsafe { GetForegroundWindow() })
         }
+    }
 
-        response::empty()
+    impl HasWindowHandle for ForegroundWindow {
+        fn window_handle(&self) -> Result<WindowHandle<'_>, HandleError> {
+            let hwnd = NonZeroIsize::new(self.0 .0 as isize).ok_or(HandleError::Unavailable)?;
+            let handle = RawWindowHandle::Win32(Win32WindowHandle::new(hwnd));
+            Ok(unsafe { WindowHandle::borrow_raw(handle) })
+        }
+    }
+
+    impl HasDisplayHandle for ForegroundWindow {
+        fn display_handle(&self) -> Result<DisplayHandle<'_>, HandleError> {
+            Err(HandleError::Unavailable)
+        }
     }
 }
@@ -0,0 +1,146 @@
+//! Plugins that live outside of kal's binary.
+
+use kal_plugin::{Action, ActionOutcome, BuiltinIcon, Icon, ResultItem};
+use serde::Deserialize;
+
+mod stdio;
+mod wasm;
+
+pub use self::stdio::*;
+pub use self::wasm::*;
+
+/// A [`ResultItem`] as described by a hosted plugin.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemDef {
+    pub id: String,
+    pub icon: Option<Icon>,
+    pub primary_text: String,
+    #[serde(default)]
+    pub secondary_text: String,
+    pub tooltip: Option<String>,
+    /// Actions supported by this item, the first one is the primary action.
+    #[serde(default = "default_actions")]
+    pub actions: Vec<ActionDef>,
+    #[serde(default)]
+    pub score: u16,
+    /// Any data the plugin wants back when an action of this item is run.
+    #[serde(default)]
+    pub payload: serde_json::Value,
+}
+
+fn default_actions() -> Vec<ActionDef> {
+    vec![ActionDef::Builtin(Action::PRIMARY.to_string())]
+}
+
+/// An [`Action`] as described by a hosted plugin.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ActionDef {
+    /// Id of a builtin action, `RunPrimary`, `RunElevated` or `OpenLocation`.
+    Builtin(String),
+    /// An action defined by the plugin.
+    #[serde(rename_all = "camelCase")]
+    Custom {
+        id: String,
+        icon: Option<Icon>,
+        description: Option<String>,
+        accelerator: Option<String>,
+        confirmation: Option<String>,
+        #[serde(default)]
+        order: i32,
+    },
+}
+
+/// An [`ActionOutcome`] as described by a hosted plugin,
+/// for example `{ "type": "setQuery", "value": "query" }`.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase", tag = "type", content = "value")]
+pub enum OutcomeDef {
+    #[default]
+    Hide,
+    KeepOpen,
+    SetQuery(String),
+    ReplaceResults(Vec<ItemDef>),
+    ShowMessage(String),
+}
+
+impl OutcomeDef {
+    /// Converts this definition into an [`ActionOutcome`],
+    /// see [`ItemDef::into_result_item`] for `plugin`.
+    pub fn into_action_outcome(self, plugin: &str) -> ActionOutcome {
+        match self {
+            OutcomeDef::Hide => ActionOutcome::Hide,
+            OutcomeDef::KeepOpen => ActionOutcome::KeepOpen,
+            OutcomeDef::SetQuery(query) => ActionOutcome::SetQuery(query),
+            OutcomeDef::ReplaceResults(items) => ActionOutcome::ReplaceResults(
+                items
+                    .into_iter()
+                    .map(|item| item.into_result_item(plugin))
+                    .collect(),
+            ),
+            OutcomeDef::ShowMessage(message) => ActionOutcome::ShowMessage(message),
+        }
+    }
+}
+
+impl ItemDef {
+    /// Converts this definition into a [`ResultItem`] owned by `plugin`.
+    ///
+    /// Its actions are dispatched back to the plugin, see [`action_params`].
+    pub fn into_result_item(self, plugin: &str) -> ResultItem {
+        let actions = self
+            .actions
+            .into_iter()
+            .filter_map(|action| match action {
+                ActionDef::Builtin(action) => match action.as_str() {
+                    Action::PRIMARY | Action::RUN_ELEVATED | Action::OPEN_LOCATION => {
+                        Some(Action::dispatch(action))
+                    }
+                    _ => {
+                        tracing::warn!("[{plugin}] Unsupported action `{action}`");
+                        None
+                    }
+                },
+                ActionDef::Custom {
+                    id,
+                    icon,
+                    description,
+                    accelerator,
+                    confirmation,
+                    order,
+                } => {
+                    let mut action = Action::dispatch(id).with_order(order);
+                    action.icon = icon;
+                    action.description = description.map(Into::into);
+                    action.accelerator = accelerator.map(Into::into);
+                    action.confirmation = confirmation.map(Into::into);
+                    Some(action)
+                }
+            })
+            .collect();
+
+        let payload = serde_json::json!({ "item": self.id, "payload": self.payload });
+
+        ResultItem {
+            id: format!("{plugin}:{}", self.id),
+            icon: self.icon.unwrap_or_else(|| BuiltinIcon::BlankFile.into()),
+            primary_text: self.primary_text,
+            secondary_text: self.secondary_text,
+            tooltip: self.tooltip,
+            actions,
+            score: self.score,
+            highlights: Default::default(),
+            payload,
+        }
+    }
+}
+
+/// Builds the params of a `run_action` request from the payload of a [`ResultItem`]
+/// created by [`ItemDef::into_result_item`], that is
+/// `{ "action": <action id>, "item": <item id>, "payload": <item payload> }`.
+fn action_params(action: &str, payload: serde_json::Value) -> serde_json::Value {
+    let mut params = payload;
+    params["action"] = action.into();
+    params
+}
@@ -0,0 +1,334 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Context;
+use kal_config::{Config, ExternalPluginConfig, PluginConfig};
+use kal_plugin::{ActionFuture, ActionProgress, PluginQueryOutput};
+use kal_utils::PathExt;
+use serde::{Deserialize, Serialize};
+use smol::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use smol::lock::Mutex;
+use smol::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use super::{action_params, ItemDef, OutcomeDef};
+
+/// A plugin running in a separate long-lived process, speaking JSON-RPC 2.0
+/// using newline-delimited messages over its stdin and stdout.
+///
+/// Supported methods:
+/// - `name`: returns the name of the plugin.
+/// - `reload`: called with `{ "config": <plugin config> }`.
+/// - `query` and `query_direct`: called with `{ "query": <query> }`
+///   and return a list of items, see [`ItemDef`].
+/// - `run_action`: called with `{ "action": <action id>, "item": <item id>, "payload": <payload> }`
+///   and optionally returns what to do next, see [`OutcomeDef`].
+///
+/// If the process crashes or doesn't reply in time, it is killed
+/// and spawned again on the next request. Replies to queries that
+/// were cancelled in the meantime are ignored.
+#[derive(Debug)]
+pub struct StdioPlugin {
+    name: String,
+    client: Arc<Client>,
+}
+
+impl StdioPlugin {
+    /// How much longer than a request a query is waited for, see [`Self::default_plugin_config`].
+    const QUERY_TIMEOUT_MARGIN: u64 = 100;
+
+    /// Spawns the plugin process and asks for its name.
+    pub async fn spawn(config: &ExternalPluginConfig) -> anyhow::Result<Self> {
+        let client = Arc::new(Client {
+            config: config.clone(),
+            process: Mutex::new(None),
+        });
+
+        let name = client.request("name", serde_json::Value::Null).await?;
+        let name = serde_json::from_value(name)?;
+
+        Ok(Self { name, client })
+    }
+
+    async fn query_items(&self, method: &str, query: &str) -> anyhow::Result<PluginQueryOutput> {
+        let params = serde_json::json!({ "query": query });
+        let items = self.client.request(method, params).await?;
+        let items = serde_json::from_value::<Vec<ItemDef>>(items)?;
+
+        Ok(items
+            .into_iter()
+            .map(|item| item.into_result_item(&self.name))
+            .collect::<Vec<_>>()
+            .into())
+    }
+}
+
+#[async_trait::async_trait]
+impl kal_plugin::Plugin for StdioPlugin {
+    fn new(_config: &Config) -> Self {
+        unreachable!("external plugins are created using `StdioPlugin::spawn`")
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn default_plugin_config(&self) -> PluginConfig {
+        // queries must outlive their request, otherwise a hung process is never
+        // killed since its query is dropped before the request times out
+        let query_timeout = self.client.config.request_timeout + Self::QUERY_TIMEOUT_MARGIN;
+
+        PluginConfig {
+            enabled: Some(true),
+            include_in_global_results: Some(true),
+            direct_activation_command: None,
+            query_timeout: Some(query_timeout),
+            inner: None,
+        }
+    }
+
+    async fn reload(&mut self, config: &Config) -> anyhow::Result<()> {
+        let inner = config.plugins.get(&self.name).and_then(|c| c.inner.clone());
+        let params = serde_json::json!({ "config": inner });
+        self.client.request("reload", params).await.map(|_| ())
+    }
+
+    async fn query(
+        &self,
+        query: &str,
+        _matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
+    ) -> anyhow::Result<PluginQueryOutput> {
+        self.query_items("query", query).await
+    }
+
+    async fn query_direct(
+        &self,
+        query: &str,
+        _matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
+    ) -> anyhow::Result<PluginQueryOutput> {
+        self.query_items("query_direct", query).await
+    }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let client = self.client.clone();
+        let name = self.name.clone();
+        let params = action_params(action, payload);
+
+        Box::pin(async move {
+            let outcome = client.request("run_action", params).await?;
+            let outcome = serde_json::from_value::<Option<OutcomeDef>>(outcome)?;
+            Ok(outcome.unwrap_or_default().into_action_outcome(&name))
+        })
+    }
+}
+
+/// Stands in for an external plugin that failed to spawn, so the failure is shown
+/// as an error item in its results until it spawns on a later reload.
+#[derive(Debug)]
+pub struct FailedStdioPlugin {
+    name: String,
+    error: String,
+}
+
+impl FailedStdioPlugin {
+    pub fn new(config: &ExternalPluginConfig, error: &anyhow::Error) -> Self {
+        Self {
+            name: config.exec.display().to_string(),
+            error: error.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl kal_plugin::Plugin for FailedStdioPlugin {
+    fn new(_config: &Config) -> Self {
+        unreachable!("failed external plugins are created using `FailedStdioPlugin::new`")
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn reload(&mut self, _config: &Config) -> anyhow::Result<()> {
+        Err(anyhow::anyhow!(self.error.clone()))
+    }
+
+    async fn query(
+        &self,
+        _query: &str,
+        _matcher: &mut kal_plugin::FuzzyMatcher,
+        _cancellation: &kal_plugin::CancellationToken,
+    ) -> anyhow::Result<PluginQueryOutput> {
+        Err(anyhow::anyhow!(self.error.clone()))
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    config: ExternalPluginConfig,
+    process: Mutex<Option<Process>>,
+}
+
+impl Client {
+    async fn request(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let mut process = self.process.lock().await;
+
+        // a request that was dropped while being written leaves a partial
+        // message in the pipe that the process can't recover from
+        if process.as_ref().is_some_and(|p| p.poisoned) {
+            process.take();
+        }
+
+        let running = match process.as_mut() {
+            Some(running) => running,
+            None => process.insert(Process::spawn(&self.config)?),
+        };
+
+        let timeout = Duration::from_millis(self.config.request_timeout);
+        let timeout = async {
+            smol::Timer::after(timeout).await;
+            anyhow::bail!("`{method}` timed out after {}ms", timeout.as_millis())
+        };
+
+        let response = match smol::future::or(running.request(method, params), timeout).await {
+            Ok(response) => response,
+            Err(e) => {
+                // the process is either dead or hung, kill it
+                // and let the next request spawn a new one
+                process.take();
+                return Err(e);
+            }
+        };
+
+        match response.error {
+            Some(error) => anyhow::bail!("{} (code: {})", error.message, error.code),
+            None => Ok(response.result.unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Process {
+    #[allow(unused)]
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: u64,
+    /// Whether a request was interrupted before being fully written.
+    poisoned: bool,
+}
+
+impl Process {
+    fn spawn(config: &ExternalPluginConfig) -> anyhow::Result<Self> {
+        let exec = config.exec.replace_env();
+
+        let mut cmd = Command::new(&exec);
+        cmd.args(&config.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+
+        let cwd = match &config.working_directory {
+            Some(cwd) => Some(cwd.replace_env()),
+            None => exec.parent().map(Path::to_path_buf),
+        };
+        if let Some(cwd) = cwd.filter(|cwd| !cwd.as_os_str().is_empty()) {
+            cmd.current_dir(cwd);
+        }
+
+        #[cfg(windows)]
+        {
+            use smol::process::windows::CommandExt;
+
+            const CREATE_NO_WINDOW: u32 = 0x08000000;
+            cmd.creation_flags(CREATE_NO_WINDOW);
+        }
+
+        let mut child = cmd
+            .spawn()
+            .with_context(|| format!("Failed to spawn {}", exec.display()))?;
+
+        let stdin = child.stdin.take().context("Failed to open stdin")?;
+        let stdout = child.stdout.take().context("Failed to open stdout")?;
+
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            next_id: 0,
+            poisoned: false,
+        })
+    }
+
+    async fn request(
+        &mut self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> anyhow::Result<Response> {
+        self.next_id += 1;
+        let id = self.next_id;
+
+        let request = Request {
+            jsonrpc: "2.0",
+            id,
+            method,
+            params,
+        };
+
+        let mut message = serde_json::to_vec(&request)?;
+        message.push(b'\n');
+
+        self.poisoned = true;
+        self.stdin.write_all(&message).await?;
+        self.stdin.flush().await?;
+        self.poisoned = false;
+
+        let mut line = String::new();
+        loop {
+            line.clear();
+
+            if self.stdout.read_line(&mut line).await? == 0 {
+                anyhow::bail!("Plugin process exited unexpectedly");
+            }
+
+            // skip anything that isn't a reply to this request, like notifications or logs
+            match serde_json::from_str::<Response>(&line) {
+                Ok(response) if response.id == Some(id) => return Ok(response),
+                _ => continue,
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: Option<u64>,
+    result: Option<serde_json::Value>,
+    error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    code: i64,
+    message: String,
+}
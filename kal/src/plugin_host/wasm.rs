@@ -0,0 +1,430 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::Context;
+use kal_config::Config;
+use kal_plugin::{
+    ActionFuture, ActionOutcome, ActionProgress, CancellationToken, FuzzyMatcher, PluginQueryOutput,
+};
+use wasmi::{
+    AsContext, AsContextMut, Caller, Engine, Extern, Instance, Linker, Memory, Module, Store,
+    StoreLimits, StoreLimitsBuilder,
+};
+
+use super::{action_params, ItemDef};
+
+/// Amount of fuel, roughly the number of executed instructions,
+/// a single call into a plugin is allowed to consume.
+const FUEL_PER_CALL: u64 = 100_000_000;
+
+/// Size in bytes the memory of a plugin is allowed to grow to.
+const MEMORY_LIMIT: usize = 256 * 1024 * 1024;
+
+/// A plugin compiled to WebAssembly and running inside a sandbox,
+/// it can't touch the file system, network or spawn processes
+/// and can only use the capabilities imported from the `kal` module.
+///
+/// Strings are passed as UTF-8 in the plugin memory, either as a `(ptr, len)` pair
+/// or, when returned, packed in an `i64` as `ptr << 32 | len`.
+///
+/// The module must export:
+/// - `memory`.
+/// - `kal_alloc(len: i32) -> i32`: allocates `len` bytes for the host to write into.
+/// - `kal_name() -> i64`: returns the name of the plugin.
+/// - `kal_query(ptr: i32, len: i32) -> i64`: called with the query
+///   and returns a JSON list of items, see [`ItemDef`].
+///
+/// and optionally:
+/// - `kal_query_direct(ptr: i32, len: i32) -> i64`: same as `kal_query`
+///   but used when the plugin is invoked directly.
+/// - `kal_reload()`: called when the config changes.
+/// - `kal_run_action(ptr: i32, len: i32) -> i64`: called with
+///   `{ "action": <action id>, "item": <item id>, "payload": <item payload> }`
+///   as JSON and returns an error message or `0` on success.
+///
+/// The `kal` module provides:
+/// - `config() -> i64`: returns the plugin config as JSON.
+/// - `fuzzy_match(haystack_ptr: i32, haystack_len: i32, needle_ptr: i32, needle_len: i32) -> i32`:
+///   returns the match score or `-1` if it doesn't match.
+/// - `open_url(ptr: i32, len: i32) -> i32`: opens an `http` or `https` url.
+/// - `copy_to_clipboard(ptr: i32, len: i32) -> i32`: copies text to the clipboard.
+/// - `log(ptr: i32, len: i32)`: writes a message to kal's log.
+///
+/// Functions returning `i32` return `0` on success and `-1` on failure.
+#[derive(Debug)]
+pub struct WasmPlugin {
+    name: String,
+    runtime: Arc<Mutex<Runtime>>,
+}
+
+impl WasmPlugin {
+    /// Compiles and instantiates the module at `path`.
+    pub fn load(path: &Path, config: &Config) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let mut runtime = Runtime::new(&bytes)?;
+
+        let name = runtime.call_str("kal_name", None)?;
+        runtime.store.data_mut().config = config.plugin_config(&name);
+
+        Ok(Self {
+            name,
+            runtime: Arc::new(Mutex::new(runtime)),
+        })
+    }
+
+    /// Collects all `.wasm` files in `dir`.
+    pub fn find_all(dir: &Path) -> Vec<PathBuf> {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .map(|e| e.path())
+            .filter(|p| p.extension().is_some_and(|e| e == "wasm"))
+            .collect()
+    }
+
+    async fn query_items(
+        &self,
+        export: &'static str,
+        query: &str,
+        cancellation: &CancellationToken,
+    ) -> anyhow::Result<PluginQueryOutput> {
+        let runtime = self.runtime.clone();
+        let query = query.to_string();
+        let cancellation = cancellation.clone();
+
+        // run off the async executor, so a slow plugin doesn't block other plugins
+        let items = smol::unblock(move || {
+            let mut runtime = runtime.lock().unwrap();
+
+            // queries pile up behind the lock while the user is typing,
+            // skip the ones that are already outdated
+            if cancellation.is_cancelled() {
+                return Ok(String::from("[]"));
+            }
+
+            let export = if runtime.has_export(export) {
+                export
+            } else {
+                "kal_query"
+            };
+
+            runtime.call_str(export, Some(&query))
+        })
+        .await?;
+
+        let items = serde_json::from_str::<Vec<ItemDef>>(&items)?;
+
+        Ok(items
+            .into_iter()
+            .map(|item| item.into_result_item(&self.name))
+            .collect::<Vec<_>>()
+            .into())
+    }
+}
+
+#[async_trait::async_trait]
+impl kal_plugin::Plugin for WasmPlugin {
+    fn new(_config: &Config) -> Self {
+        unreachable!("wasm plugins are created using `WasmPlugin::load`")
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn reload(&mut self, config: &Config) -> anyhow::Result<()> {
+        let runtime = self.runtime.clone();
+        let plugin_config = config.plugin_config(&self.name);
+        let transliterate = config.general.transliterate;
+
+        // run off the async executor, the lock may be held by a query
+        // and `kal_reload` may be slow, like queries
+        smol::unblock(move || {
+            let mut runtime = runtime.lock().unwrap();
+            let state = runtime.store.data_mut();
+            state.config = plugin_config;
+            state.matcher.set_transliterate(transliterate);
+
+            if runtime.has_export("kal_reload") {
+                let reload = runtime
+                    .instance
+                    .get_typed_func::<(), ()>(&runtime.store, "kal_reload")?;
+                runtime.refuel()?;
+                reload.call(&mut runtime.store, ())?;
+            }
+
+            Ok(())
+        })
+        .await
+    }
+
+    async fn query(
+        &self,
+        query: &str,
+        _matcher: &mut FuzzyMatcher,
+        cancellation: &CancellationToken,
+    ) -> anyhow::Result<PluginQueryOutput> {
+        self.query_items("kal_query", query, cancellation).await
+    }
+
+    async fn query_direct(
+        &self,
+        query: &str,
+        _matcher: &mut FuzzyMatcher,
+        cancellation: &CancellationToken,
+    ) -> anyhow::Result<PluginQueryOutput> {
+        self.query_items("kal_query_direct", query, cancellation)
+            .await
+    }
+
+    fn run_action(
+        &self,
+        action: &str,
+        payload: serde_json::Value,
+        _progress: ActionProgress,
+    ) -> ActionFuture {
+        let runtime = self.runtime.clone();
+        let params = action_params(action, payload).to_string();
+
+        Box::pin(smol::unblock(move || {
+            let error = runtime
+                .lock()
+                .unwrap()
+                .call_str("kal_run_action", Some(&params))?;
+            if error.is_empty() {
+                Ok(ActionOutcome::Hide)
+            } else {
+                Err(anyhow::anyhow!(error))
+            }
+        }))
+    }
+}
+
+/// State accessible to the host functions.
+#[derive(Default)]
+struct HostState {
+    config: serde_json::Value,
+    matcher: FuzzyMatcher,
+    limits: StoreLimits,
+}
+
+struct Runtime {
+    store: Store<HostState>,
+    instance: Instance,
+    memory: Memory,
+}
+
+impl std::fmt::Debug for Runtime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Runtime").finish_non_exhaustive()
+    }
+}
+
+impl Runtime {
+    fn new(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, bytes)?;
+
+        let state = HostState {
+            limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+            ..Default::default()
+        };
+        let mut store = Store::new(&engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(FUEL_PER_CALL).map_err(wasmi::Error::from)?;
+
+        let mut linker = Linker::new(&engine);
+        link_host_functions(&mut linker)?;
+
+        let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .context("Plugin doesn't export `memory`")?;
+
+        Ok(Self {
+            store,
+            instance,
+            memory,
+        })
+    }
+
+    fn has_export(&self, name: &str) -> bool {
+        self.instance.get_export(&self.store, name).is_some()
+    }
+
+    fn refuel(&mut self) -> anyhow::Result<()> {
+        self.store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| wasmi::Error::from(e).into())
+    }
+
+    /// Calls `export` with an optional string argument and reads back the returned string.
+    fn call_str(&mut self, export: &str, arg: Option<&str>) -> anyhow::Result<String> {
+        self.refuel()?;
+
+        let packed = match arg {
+            Some(arg) => {
+                let (ptr, len) = write_str(&mut self.store, &self.instance, self.memory, arg)?;
+                self.instance
+                    .get_typed_func::<(i32, i32), i64>(&self.store, export)?
+                    .call(&mut self.store, (ptr, len))?
+            }
+            None => self
+                .instance
+                .get_typed_func::<(), i64>(&self.store, export)?
+                .call(&mut self.store, ())?,
+        };
+
+        let (ptr, len) = unpack(packed);
+        read_str(&self.store, self.memory, ptr, len)
+    }
+}
+
+fn pack(ptr: i32, len: i32) -> i64 {
+    ((ptr as u32 as i64) << 32) | len as u32 as i64
+}
+
+fn unpack(packed: i64) -> (i32, i32) {
+    ((packed >> 32) as i32, packed as i32)
+}
+
+/// Reads the string at `ptr`, checking it's inside `memory` before copying it,
+/// since a plugin can pass any `len`.
+fn read_str(ctx: impl AsContext, memory: Memory, ptr: i32, len: i32) -> anyhow::Result<String> {
+    let start = ptr as u32 as usize;
+    let bytes = start
+        .checked_add(len as u32 as usize)
+        .and_then(|end| memory.data(ctx.as_context()).get(start..end))
+        .context("String is outside of the plugin memory")?;
+    String::from_utf8(bytes.to_vec()).map_err(Into::into)
+}
+
+fn write_str<T>(
+    mut ctx: impl AsContextMut<Data = T>,
+    instance: &Instance,
+    memory: Memory,
+    s: &str,
+) -> anyhow::Result<(i32, i32)> {
+    let len = i32::try_from(s.len())?;
+    let alloc = instance.get_typed_func::<i32, i32>(&ctx, "kal_alloc")?;
+    let ptr = alloc.call(&mut ctx, len)?;
+    memory
+        .write(&mut ctx, ptr as u32 as usize, s.as_bytes())
+        .map_err(wasmi::Error::from)?;
+    Ok((ptr, len))
+}
+
+fn caller_memory(caller: &Caller<'_, HostState>) -> Result<Memory, wasmi::Error> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("Plugin doesn't export `memory`"))
+}
+
+fn caller_str(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    let memory = caller_memory(caller)?;
+    read_str(caller, memory, ptr, len).map_err(|e| wasmi::Error::new(e.to_string()))
+}
+
+fn status(result: anyhow::Result<()>) -> i32 {
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            tracing::warn!("[WasmPlugin] {e}");
+            -1
+        }
+    }
+}
+
+fn open_url(url: &str) -> anyhow::Result<()> {
+    let url = url::Url::parse(url)?;
+    // other schemes, like `file`, could be used to launch arbitrary executables
+    if !matches!(url.scheme(), "http" | "https") {
+        anyhow::bail!("Refusing to open `{url}`, only http(s) urls are allowed");
+    }
+    kal_utils::open_url(&url)
+}
+
+fn copy_to_clipboard(text: String) -> anyhow::Result<()> {
+    let mut clipboard = arboard::Clipboard::new()?;
+    clipboard.set_text(text)?;
+    Ok(())
+}
+
+fn link_host_functions(linker: &mut Linker<HostState>) -> anyhow::Result<()> {
+    linker.func_wrap(
+        "kal",
+        "config",
+        |mut caller: Caller<'_, HostState>| -> Result<i64, wasmi::Error> {
+            let config = caller.data().config.to_string();
+            let memory = caller_memory(&caller)?;
+            let alloc = caller
+                .get_export("kal_alloc")
+                .and_then(Extern::into_func)
+                .ok_or_else(|| wasmi::Error::new("Plugin doesn't export `kal_alloc`"))?
+                .typed::<i32, i32>(&caller)?;
+
+            let len = i32::try_from(config.len())
+                .map_err(|_| wasmi::Error::new("Config is too large to pass to the plugin"))?;
+            let ptr = alloc.call(&mut caller, len)?;
+            memory.write(&mut caller, ptr as u32 as usize, config.as_bytes())?;
+
+            Ok(pack(ptr, len))
+        },
+    )?;
+
+    linker.func_wrap(
+        "kal",
+        "fuzzy_match",
+        |mut caller: Caller<'_, HostState>,
+         haystack_ptr: i32,
+         haystack_len: i32,
+         needle_ptr: i32,
+         needle_len: i32|
+         -> Result<i32, wasmi::Error> {
+            let haystack = caller_str(&caller, haystack_ptr, haystack_len)?;
+            let needle = caller_str(&caller, needle_ptr, needle_len)?;
+            let matcher = &mut caller.data_mut().matcher;
+            Ok(matcher
+                .fuzzy_match(&haystack, &needle)
+                .map(i32::from)
+                .unwrap_or(-1))
+        },
+    )?;
+
+    linker.func_wrap(
+        "kal",
+        "open_url",
+        |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i32, wasmi::Error> {
+            let url = caller_str(&caller, ptr, len)?;
+            Ok(status(open_url(&url)))
+        },
+    )?;
+
+    linker.func_wrap(
+        "kal",
+        "copy_to_clipboard",
+        |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<i32, wasmi::Error> {
+            let text = caller_str(&caller, ptr, len)?;
+            Ok(status(copy_to_clipboard(text)))
+        },
+    )?;
+
+    linker.func_wrap(
+        "kal",
+        "log",
+        |caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            let message = caller_str(&caller, ptr, len)?;
+            tracing::info!("[WasmPlugin] {message}");
+            Ok(())
+        },
+    )?;
+
+    Ok(())
+}
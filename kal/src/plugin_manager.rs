@@ -1,13 +1,71 @@
+use std::collections::HashSet;
+use std::future::Future;
 use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use kal_config::Config;
-use kal_plugin::{Plugin, ResultItem};
-use smol::lock::RwLock;
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use kal_config::{
+    Config, ConfigDiagnostic, ConfigSchema, ExternalPluginConfig, KeyboardLayout, Severity,
+};
+use kal_plugin::{
+    ActionFuture, ActionOutcome, ActionProgress, CancellationToken, FuzzyMatcher, IndexingStatus,
+    Plugin, PluginEvents, PluginQueryOutput, ResultItem,
+};
+use serde::Serialize;
+use smol::lock::Mutex;
+
+use crate::history::{Frecency, History};
+use crate::plugin_host::{FailedStdioPlugin, StdioPlugin, WasmPlugin};
+use crate::plugin_stats::PluginStats;
+
+/// Subtracted from the score of results found by a query
+/// remapped to another keyboard layout, see [`PluginManager::layout_rewrites`].
+const LAYOUT_REWRITE_PENALTY: u16 = 20;
+
+/// Callback receiving batches of results while querying plugins.
+pub type OnBatch<'a> = dyn Fn(Vec<QueryResult>) + Send + Sync + 'a;
+
+/// A [`ResultItem`] along with the name of the plugin that produced it,
+/// which runs its [dispatched](kal_plugin::Action::dispatch) actions.
+#[derive(Serialize, Debug)]
+pub struct QueryResult {
+    #[serde(skip)]
+    pub plugin: Arc<str>,
+    #[serde(flatten)]
+    pub item: ResultItem,
+}
+
+/// Where a [`PluginEntry`] was loaded from.
+#[derive(Debug, PartialEq)]
+enum PluginSource {
+    Builtin,
+    /// An external plugin spawned from this config.
+    Stdio(ExternalPluginConfig),
+    /// An external plugin that failed to spawn from this config, see [`FailedStdioPlugin`].
+    FailedStdio(ExternalPluginConfig),
+    /// A WebAssembly plugin loaded from this file.
+    Wasm(PathBuf),
+}
 
 pub struct PluginEntry {
     pub enabled: bool,
     pub include_in_global_results: bool,
     pub direct_activation_command: Option<String>,
+    /// See [`Plugin::direct_activation_keywords`], empty while disabled.
+    keywords: Vec<String>,
+    /// Time in milliseconds to wait for this plugin's results on each query.
+    pub query_timeout: u64,
+    /// Max number of [candidates](kal_plugin::Candidate) to build results for on each query.
+    max_results: usize,
+    source: PluginSource,
+    /// Each plugin gets its own matcher so they can be queried concurrently.
+    matcher: Mutex<FuzzyMatcher>,
+    /// Updated by queries running concurrently, so it has its own lock.
+    stats: std::sync::Mutex<PluginStats>,
     plugin: Box<dyn Plugin>,
 }
 
@@ -17,6 +75,9 @@ impl std::fmt::Debug for PluginEntry {
             .field("enabled", &self.enabled)
             .field("include_in_global_results", &self.include_in_global_results)
             .field("direct_activation_command", &self.direct_activation_command)
+            .field("keywords", &self.keywords)
+            .field("query_timeout", &self.query_timeout)
+            .field("source", &self.source)
             .field("plugin_name", &self.plugin.name())
             .finish()
     }
@@ -45,13 +106,25 @@ impl PluginEntry {
     fn new<P: Plugin + 'static>(plugin: P) -> Self {
         let config = plugin.default_plugin_config();
         Self {
+            query_timeout: config.query_timeout(),
             enabled: config.enabled.unwrap_or(true),
             include_in_global_results: config.include_in_global_results.unwrap_or(true),
             direct_activation_command: config.direct_activation_command,
+            keywords: Vec::new(),
+            max_results: usize::MAX,
+            source: PluginSource::Builtin,
+            matcher: Mutex::new(FuzzyMatcher::default()),
+            stats: Default::default(),
             plugin: Box::new(plugin),
         }
     }
 
+    fn with_source<P: Plugin + 'static>(plugin: P, source: PluginSource) -> Self {
+        let mut entry = Self::new(plugin);
+        entry.source = source;
+        entry
+    }
+
     /// Convenient method to construct an error [ResultItem] for this plugin.
     fn error_item(&self, error: String) -> ResultItem {
         ResultItem {
@@ -62,24 +135,287 @@ impl PluginEntry {
             tooltip: None,
             actions: vec![],
             score: 0,
+            highlights: Default::default(),
+            payload: serde_json::Value::Null,
         }
     }
 
-    pub fn is_direct_invoke(&self, query: &str) -> bool {
-        self.direct_activation_command
-            .as_deref()
-            .map(|c| query.starts_with(c))
-            .unwrap_or(false)
+    /// Convenient method to construct a warning [ResultItem] for this plugin.
+    fn warning_item(&self, warning: String) -> ResultItem {
+        ResultItem {
+            icon: crate::icon::BuiltinIcon::Warning.into(),
+            ..self.error_item(warning)
+        }
     }
 
-    pub fn direct_invoke_len(&self) -> usize {
-        self.direct_activation_command
-            .as_ref()
-            .map(|c| c.len())
-            .unwrap_or_default()
+    /// Builds a [ResultItem] describing the health of this plugin, see [`PluginStats`].
+    fn diagnostics_item(&self) -> ResultItem {
+        let stats = self.stats.lock().unwrap();
+
+        let mut details = Vec::new();
+
+        if !self.enabled {
+            details.push("Disabled".to_owned());
+        }
+        if let Some(duration) = stats.reload_duration {
+            details.push(format!("Reloaded in {}ms", duration.as_millis()));
+        }
+        if let Some(items) = stats.indexed_items {
+            details.push(format!("Items indexed: {items}"));
+        }
+        if let Some(results) = stats.last_results {
+            details.push(format!("Results on last query: {results}"));
+        }
+        if let Some(p50) = stats.latency_percentile(50) {
+            let p95 = stats.latency_percentile(95).unwrap_or(p50);
+            let (p50, p95) = (p50.as_millis(), p95.as_millis());
+            details.push(format!("Queries p50 {p50}ms, p95 {p95}ms"));
+        }
+        if stats.timeouts > 0 {
+            details.push(format!("Timeouts: {}", stats.timeouts));
+        }
+
+        let details = details.join(" · ");
+
+        let (icon, score, secondary_text) = match &stats.last_error {
+            Some(error) => (
+                crate::icon::BuiltinIcon::Error,
+                2,
+                format!("{error} · {details}"),
+            ),
+            None if !self.enabled || stats.timeouts > 0 => {
+                (crate::icon::BuiltinIcon::Warning, 1, details)
+            }
+            None => (crate::icon::BuiltinIcon::Admin, 0, details),
+        };
+
+        ResultItem {
+            id: format!("{}:{}", PluginManager::DIAGNOSTICS_COMMAND, self.name()),
+            icon: icon.into(),
+            primary_text: self.name().to_owned(),
+            tooltip: stats.last_error.clone(),
+            secondary_text,
+            actions: vec![],
+            score,
+            highlights: Default::default(),
+            payload: serde_json::Value::Null,
+        }
+    }
+
+    /// Queries this plugin, passing each batch of results to `on_batch` as soon as
+    /// it is available, giving up after its query timeout and reporting errors
+    /// and timeouts as result items.
+    ///
+    /// Scores of results the user launched before are boosted by their `frecency`.
+    ///
+    /// Once `query` finished, each of `rewrites` is queried, adding the results
+    /// that weren't found already with a small score penalty, see [`Self::query_rewrites`].
+    ///
+    /// Once `cancellation` is cancelled, the query is dropped and nothing else is reported.
+    async fn query_with_timeout(
+        &self,
+        query: &str,
+        rewrites: &[String],
+        direct: bool,
+        frecency: &Frecency,
+        cancellation: &CancellationToken,
+        on_batch: &OnBatch<'_>,
+    ) {
+        let start = Instant::now();
+        let reported = AtomicUsize::new(0);
+
+        // results remember their plugin, so it can run their dispatched actions later
+        let plugin: Arc<str> = self.name().into();
+        let on_batch = |batch: Vec<ResultItem>| {
+            reported.fetch_add(batch.len(), Ordering::Relaxed);
+            let batch = batch.into_iter().map(|mut item| {
+                item.score = item.score.saturating_add(frecency.boost(&plugin, &item.id));
+                let plugin = plugin.clone();
+                QueryResult { plugin, item }
+            });
+            on_batch(batch.collect())
+        };
+
+        let run = async {
+            let mut matcher = self.matcher.lock().await;
+
+            let output = if direct {
+                self.query_direct(query, &mut matcher, cancellation).await?
+            } else {
+                self.query(query, &mut matcher, cancellation).await?
+            };
+            let output = self.materialize_candidates(output, query, &mut matcher, frecency);
+
+            // ids of reported results, so rewrites don't report the same result twice
+            let mut seen = HashSet::new();
+
+            let mut stream = output.into_stream();
+            while let Some(batch) = stream.next().await {
+                if !rewrites.is_empty() {
+                    seen.extend(batch.iter().map(|item| item.id.clone()));
+                }
+                on_batch(batch);
+            }
+
+            anyhow::Ok(seen)
+        };
+
+        let Some(outcome) = self.with_timeout(run, cancellation).await else {
+            return;
+        };
+
+        let latency = start.elapsed();
+
+        let seen = match outcome {
+            Some(Ok(seen)) => {
+                let results = reported.load(Ordering::Relaxed);
+                self.stats.lock().unwrap().record_query(latency, results);
+                seen
+            }
+            Some(Err(e)) => {
+                let error = e.to_string();
+                self.stats
+                    .lock()
+                    .unwrap()
+                    .record_query_error(latency, error.clone());
+                on_batch(vec![self.error_item(error)]);
+                return;
+            }
+            None => {
+                self.stats.lock().unwrap().record_timeout(latency);
+                let warning = format!("Timed out after {}ms", self.query_timeout);
+                on_batch(vec![self.warning_item(warning)]);
+                return;
+            }
+        };
+
+        if !rewrites.is_empty() {
+            let rewrites = self.query_rewrites(rewrites, seen, frecency, cancellation, &on_batch);
+            self.with_timeout(rewrites, cancellation).await;
+        }
+    }
+
+    /// Queries each of `rewrites` in turn, adding the results that aren't in `seen`
+    /// with a small score penalty.
+    ///
+    /// Rewrites are only a guess, so they are queried after the results of the
+    /// actual query are reported, and their errors and timeouts aren't reported.
+    async fn query_rewrites(
+        &self,
+        rewrites: &[String],
+        mut seen: HashSet<String>,
+        frecency: &Frecency,
+        cancellation: &CancellationToken,
+        on_batch: &(dyn Fn(Vec<ResultItem>) + Send + Sync),
+    ) {
+        let mut matcher = self.matcher.lock().await;
+
+        for rewrite in rewrites {
+            let Ok(output) = self.query(rewrite, &mut matcher, cancellation).await else {
+                continue;
+            };
+            let output = self.materialize_candidates(output, rewrite, &mut matcher, frecency);
+
+            let mut stream = output.into_stream();
+            while let Some(batch) = stream.next().await {
+                let batch = batch
+                    .into_iter()
+                    .filter(|item| seen.insert(item.id.clone()))
+                    .map(|mut item| {
+                        item.score = item.score.saturating_sub(LAYOUT_REWRITE_PENALTY);
+                        item
+                    })
+                    .collect::<Vec<_>>();
+
+                if !batch.is_empty() {
+                    on_batch(batch);
+                }
+            }
+        }
+    }
+
+    /// Runs `future` until it finishes, returning `Some(Some(output))`,
+    /// this plugin's query timeout elapses, returning `Some(None)`,
+    /// or `cancellation` is cancelled, returning `None`.
+    async fn with_timeout<T>(
+        &self,
+        future: impl Future<Output = T>,
+        cancellation: &CancellationToken,
+    ) -> Option<Option<T>> {
+        let future = async { Some(future.await) };
+
+        let timeout = async {
+            smol::Timer::after(Duration::from_millis(self.query_timeout)).await;
+            None
+        };
+
+        let finished = async { Some(smol::future::or(future, timeout).await) };
+
+        let cancelled = async {
+            cancellation.cancelled().await;
+            None
+        };
+
+        // an outdated query doesn't report anything, not even errors or timeouts
+        smol::future::or(cancelled, finished).await
+    }
+
+    /// Builds the results of the best [`PluginQueryOutput::Candidates`] returned for `query`,
+    /// ranked by their frecency boosted score like the results of all plugins are.
+    ///
+    /// No more than `max_results` of them can be shown, even if this plugin
+    /// returns the best matches of all plugins, so the rest are never built.
+    fn materialize_candidates(
+        &self,
+        output: PluginQueryOutput,
+        query: &str,
+        matcher: &mut FuzzyMatcher,
+        frecency: &Frecency,
+    ) -> PluginQueryOutput {
+        let PluginQueryOutput::Candidates(mut candidates) = output else {
+            return output;
+        };
+
+        // only used for ranking, results get their own score when built
+        for candidate in &mut candidates {
+            let boost = frecency.boost(self.name(), &candidate.id);
+            candidate.score = candidate.score.saturating_add(boost);
+        }
+
+        if candidates.len() > self.max_results {
+            candidates.select_nth_unstable_by(self.max_results, |a, b| b.score.cmp(&a.score));
+            candidates.truncate(self.max_results);
+        }
+
+        candidates
+            .iter()
+            .filter_map(|candidate| self.materialize(candidate, query, matcher))
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Returns the query to pass to [`Plugin::query_direct`] if `query` invokes this plugin
+    /// directly, without its direct activation command or with the keyword it starts with.
+    pub fn direct_query<'a>(&self, query: &'a str) -> Option<&'a str> {
+        let command = self.direct_activation_command.as_deref();
+        if let Some(rest) = command.and_then(|c| query.strip_prefix(c)) {
+            return Some(rest.trim());
+        }
+
+        self.keywords.iter().find_map(|keyword| {
+            let rest = query.strip_prefix(keyword.as_str())?;
+            // the keyword must be a whole word, `gh` shouldn't match `ghost`
+            let whole_word = rest.is_empty() || rest.starts_with(char::is_whitespace);
+            whole_word.then(|| query.trim())
+        })
     }
 
     fn update_from_config(&mut self, config: &Config) {
+        self.matcher
+            .get_mut()
+            .set_transliterate(config.general.transliterate);
+        self.max_results = config.general.max_results;
+
         let default_c = self.default_plugin_config();
 
         match config.plugins.get(self.name()) {
@@ -89,11 +425,13 @@ impl PluginEntry {
                     c.include_in_global_results_or(default_c.include_in_global_results);
                 self.direct_activation_command =
                     c.direct_activation_command_or(default_c.direct_activation_command.as_ref());
+                self.query_timeout = c.query_timeout_or(default_c.query_timeout);
             }
             None => {
                 self.enabled = default_c.enabled();
                 self.include_in_global_results = default_c.include_in_global_results();
                 self.direct_activation_command = default_c.direct_activation_command();
+                self.query_timeout = default_c.query_timeout();
             }
         };
     }
@@ -102,91 +440,503 @@ impl PluginEntry {
 #[derive(Debug)]
 pub struct PluginManager {
     pub plugins: Vec<PluginEntry>,
+    /// Directory to load WebAssembly plugins from.
+    pub wasm_plugins_dir: PathBuf,
     pub max_results: usize,
-    pub fuzzy_matcher: RwLock<kal_plugin::FuzzyMatcher>,
+    /// Results launched by the user, used to rank results they use often and recently higher.
+    pub history: Arc<History>,
+    pub frecency_weight: u16,
+    /// Days after which a launch counts half as much towards the frecency boost.
+    pub frecency_half_life: f64,
+    /// Pairs of layouts the query is also tried in, see [`Self::layout_rewrites`].
+    pub keyboard_layouts: Vec<[KeyboardLayout; 2]>,
+    /// Receives the events plugins send outside of queries, see [`Plugin::init_handle`].
+    pub events: PluginEvents,
+    /// WebAssembly plugins that failed to load on the last reload, along with the error,
+    /// see [`Self::diagnostics`]. External plugins are kept as a [`FailedStdioPlugin`] instead.
+    pub load_errors: Vec<(String, String)>,
+    /// Problems found in the config on the last reload, including
+    /// the configs plugins read while reloading, see [`Config::diagnostics`].
+    pub config_diagnostics: Vec<ConfigDiagnostic>,
 }
 
 impl PluginManager {
-    pub fn new(plugins: Vec<PluginEntry>) -> Self {
+    /// Direct command listing the health of each plugin, see [`Self::diagnostics`].
+    pub const DIAGNOSTICS_COMMAND: &str = "kal:plugins";
+
+    pub fn new(plugins: Vec<PluginEntry>, wasm_plugins_dir: PathBuf) -> Self {
         Self {
             plugins,
+            wasm_plugins_dir,
             max_results: 0,
-            fuzzy_matcher: RwLock::new(kal_plugin::FuzzyMatcher::default()),
+            history: Default::default(),
+            frecency_weight: 0,
+            frecency_half_life: 0.0,
+            keyboard_layouts: Vec::new(),
+            events: PluginEvents::default(),
+            load_errors: Vec::new(),
+            config_diagnostics: Vec::new(),
         }
     }
 
-    pub fn all(config: &Config) -> Self {
-        Self::new(vec![
+    pub fn all(config: &Config, data_dir: &Path, events: PluginEvents) -> Self {
+        let plugins = vec![
             kal_plugin_app_launcher::Plugin::new(config).into(),
             kal_plugin_calculator::Plugin::new(config).into(),
             kal_plugin_directory_indexer::Plugin::new(config).into(),
             kal_plugin_everything::Plugin::new(config).into(),
+            kal_plugin_script_filter::Plugin::new(config).into(),
             kal_plugin_shell::Plugin::new(config).into(),
             kal_plugin_system_commands::Plugin::new(config).into(),
             kal_plugin_vscode_workspaces::Plugin::new(config).into(),
             kal_plugin_workflows::Plugin::new(config).into(),
-        ])
+        ];
+
+        let mut plugin_manager = Self::new(plugins, data_dir.join("plugins"));
+        plugin_manager.events = events;
+
+        let cache_dir = data_dir.join("cache");
+        for plugin in plugin_manager.plugins.iter_mut() {
+            plugin.init_cache(&cache_dir);
+            let handle = plugin_manager.events.handle(plugin.name());
+            plugin.init_handle(handle);
+        }
+
+        plugin_manager.history = Arc::new(History::load(data_dir.join("history.json")));
+        plugin_manager
+    }
+
+    /// Adds the options of each builtin plugin to the config JSON schema,
+    /// keep in sync with the plugins of [`PluginManager::all`].
+    pub fn builtin_config_schemas(schema: &mut ConfigSchema) {
+        kal_plugin_app_launcher::Plugin::config_schema(schema);
+        kal_plugin_calculator::Plugin::config_schema(schema);
+        kal_plugin_directory_indexer::Plugin::config_schema(schema);
+        kal_plugin_everything::Plugin::config_schema(schema);
+        kal_plugin_script_filter::Plugin::config_schema(schema);
+        kal_plugin_shell::Plugin::config_schema(schema);
+        kal_plugin_system_commands::Plugin::config_schema(schema);
+        kal_plugin_vscode_workspaces::Plugin::config_schema(schema);
+        kal_plugin_workflows::Plugin::config_schema(schema);
+    }
+
+    /// Adds a plugin loaded while reloading, like an external or WebAssembly plugin.
+    ///
+    /// Fails if a plugin with the same name is already loaded,
+    /// since plugins are found by name to run the actions of their results.
+    fn add_plugin(&mut self, mut plugin: PluginEntry) -> anyhow::Result<()> {
+        if self.plugins.iter().any(|p| p.name() == plugin.name()) {
+            anyhow::bail!("A plugin named `{}` is already loaded", plugin.name());
+        }
+
+        let handle = self.events.handle(plugin.name());
+        plugin.init_handle(handle);
+        self.plugins.push(plugin);
+
+        Ok(())
+    }
+
+    /// Spawns newly added external plugins and drops the ones
+    /// removed from `config`, plugins that failed to spawn are spawned again.
+    ///
+    /// Plugins that fail to spawn are replaced by a [`FailedStdioPlugin`],
+    /// so the failure shows up in the results.
+    async fn sync_external_plugins(&mut self, config: &Config) {
+        self.plugins.retain(|p| match &p.source {
+            PluginSource::Stdio(c) => config.external_plugins.contains(c),
+            PluginSource::FailedStdio(_) => false,
+            _ => true,
+        });
+
+        for c in &config.external_plugins {
+            let source = PluginSource::Stdio(c.clone());
+            if self.plugins.iter().any(|p| p.source == source) {
+                continue;
+            }
+
+            let added = match StdioPlugin::spawn(c).await {
+                Ok(plugin) => self.add_plugin(PluginEntry::with_source(plugin, source)),
+                Err(e) => Err(e),
+            };
+
+            if let Err(e) = added {
+                tracing::error!(
+                    "Failed to spawn external plugin `{}`: {e}",
+                    c.exec.display()
+                );
+
+                let plugin = FailedStdioPlugin::new(c, &e);
+                let source = PluginSource::FailedStdio(c.clone());
+                // the exec path doesn't clash with plugin names, unless listed twice
+                if let Err(e) = self.add_plugin(PluginEntry::with_source(plugin, source)) {
+                    tracing::error!("{e}");
+                }
+            }
+        }
+    }
+
+    /// Loads newly added WebAssembly plugins and drops the ones
+    /// whose files were removed.
+    fn sync_wasm_plugins(&mut self, config: &Config) {
+        let paths = WasmPlugin::find_all(&self.wasm_plugins_dir);
+
+        self.plugins.retain(|p| match &p.source {
+            PluginSource::Wasm(path) => paths.contains(path),
+            _ => true,
+        });
+
+        for path in paths {
+            let loaded = |p: &PluginEntry| matches!(&p.source, PluginSource::Wasm(l) if *l == path);
+            if self.plugins.iter().any(loaded) {
+                continue;
+            }
+
+            let source = PluginSource::Wasm(path.clone());
+            let loaded = WasmPlugin::load(&path, config)
+                .and_then(|plugin| self.add_plugin(PluginEntry::with_source(plugin, source)));
+
+            if let Err(e) = loaded {
+                let path = path.display().to_string();
+                tracing::error!("Failed to load wasm plugin `{path}`: {e}");
+                self.load_errors.push((path, e.to_string()));
+            }
+        }
     }
 
     pub async fn reload(&mut self, config: &Config) {
         self.max_results = config.general.max_results;
+        self.frecency_weight = config.general.frecency_weight;
+        self.frecency_half_life = config.general.frecency_half_life;
+        self.keyboard_layouts = config.general.keyboard_layouts.clone();
+
+        self.load_errors.clear();
+        self.sync_external_plugins(config).await;
+        self.sync_wasm_plugins(config);
 
         for plugin in self.plugins.iter_mut() {
             plugin.update_from_config(config);
 
             // reload plugin reload if enabled
             if plugin.enabled {
-                if let Err(e) = plugin.reload(config).await {
+                let start = Instant::now();
+                let reloaded = plugin.reload(config).await;
+
+                if let Err(e) = &reloaded {
                     tracing::error!("Failed to reload `{}`: {e}", plugin.name());
                 }
+
+                let stats = plugin.stats.get_mut().unwrap();
+                stats.record_reload(start.elapsed(), &reloaded);
+
+                // the items matched by the plugin may have changed
+                plugin.matcher.get_mut().clear_candidates();
+            }
+
+            plugin.keywords = match plugin.enabled {
+                true => plugin.direct_activation_keywords(),
+                false => Vec::new(),
+            };
+        }
+
+        // the config of an external plugin that failed to spawn
+        // can't be told apart from the config of an unknown plugin
+        let failed = |p: &PluginEntry| matches!(p.source, PluginSource::FailedStdio(_));
+        if !self.plugins.iter().any(failed) {
+            for name in config.plugins.keys() {
+                if !self.plugins.iter().any(|p| p.name() == name) {
+                    config.report_unknown_plugin(name);
+                }
             }
         }
+
+        self.config_diagnostics = config.diagnostics();
     }
 
-    pub async fn query(&mut self, query: &str) -> anyhow::Result<Vec<ResultItem>> {
-        let mut results = Vec::with_capacity(self.max_results);
+    /// Queries plugins, passing each batch of results
+    /// to `on_batch` as soon as a plugin produces it.
+    ///
+    /// Results the user launched often and recently get their score boosted,
+    /// see [`History::frecency`].
+    ///
+    /// Returns early once `cancellation` is cancelled, dropping any in-flight plugin queries.
+    pub async fn query(
+        &self,
+        query: &str,
+        cancellation: &CancellationToken,
+        on_batch: &OnBatch<'_>,
+    ) {
+        self.query_plugins(query, None, cancellation, on_batch)
+            .await
+    }
+
+    /// Same as [`Self::query`] but only queries `plugin`, if it would be queried for `query`.
+    pub async fn requery(
+        &self,
+        plugin: &str,
+        query: &str,
+        cancellation: &CancellationToken,
+        on_batch: &OnBatch<'_>,
+    ) {
+        self.query_plugins(query, Some(plugin), cancellation, on_batch)
+            .await
+    }
 
-        let plugins = &mut self.plugins; // mutability splitting
+    /// Queries the plugins that should be queried for `query`, or only `only` if set.
+    async fn query_plugins(
+        &self,
+        query: &str,
+        only: Option<&str>,
+        cancellation: &CancellationToken,
+        on_batch: &OnBatch<'_>,
+    ) {
+        if let Some(filter) = query.strip_prefix(Self::DIAGNOSTICS_COMMAND) {
+            if only.is_none() {
+                on_batch(self.diagnostics(filter.trim()));
+            }
+            return;
+        }
 
-        // it is fine to block here since only one query can be processed at a time
-        let mut matcher = self.fuzzy_matcher.write().await;
+        let weight = self.frecency_weight;
+        let frecency = self
+            .history
+            .frecency(query, weight, self.frecency_half_life);
 
         // check if a plugin is being invoked directly
-        if let Some(plugin) = plugins.iter_mut().find(|p| p.is_direct_invoke(query)) {
-            let direct_invoke_len = plugin.direct_invoke_len();
-            let new_query = &query[direct_invoke_len..].trim();
-
-            match plugin.query_direct(new_query, &mut matcher).await {
-                Ok(res) => res.extend_into(&mut results),
-                Err(err) => results.push(plugin.error_item(err.to_string())),
+        if let Some((plugin, new_query)) = self.direct_plugin(query) {
+            if only.is_some_and(|only| only != plugin.name()) {
+                return;
             }
+
+            plugin
+                .query_with_timeout(new_query, &[], true, &frecency, cancellation, on_batch)
+                .await
         } else {
             // otherwise, query all queriable plugins
             let trimmed_query = query.trim();
+            let rewrites = self.layout_rewrites(trimmed_query);
+
+            let queries = self
+                .global_plugins()
+                .filter(|p| only.is_none_or(|only| only == p.name()))
+                .map(|p| {
+                    p.query_with_timeout(
+                        trimmed_query,
+                        &rewrites,
+                        false,
+                        &frecency,
+                        cancellation,
+                        on_batch,
+                    )
+                });
+
+            // query all plugins concurrently, so a slow plugin doesn't hold up the others
+            futures::future::join_all(queries).await;
+        }
+    }
+
+    /// Whether `plugin` would be queried for `query`, see [`Self::query`].
+    pub fn is_queried(&self, plugin: &str, query: &str) -> bool {
+        if query.starts_with(Self::DIAGNOSTICS_COMMAND) {
+            return false;
+        }
+
+        match self.direct_plugin(query) {
+            Some((direct, _)) => direct.name() == plugin,
+            None => self.global_plugins().any(|p| p.name() == plugin),
+        }
+    }
+
+    /// The plugin `query` invokes directly, if any,
+    /// along with the query to pass to it, see [`PluginEntry::direct_query`].
+    fn direct_plugin<'a>(&self, query: &'a str) -> Option<(&PluginEntry, &'a str)> {
+        self.plugins
+            .iter()
+            .find_map(|p| p.direct_query(query).map(|query| (p, query)))
+    }
+
+    /// The plugins queried when none is invoked directly, they are:
+    ///   1. enabled
+    ///   2. should be included in global results
+    fn global_plugins(&self) -> impl Iterator<Item = &PluginEntry> {
+        self.plugins
+            .iter()
+            .filter(|p| p.enabled && p.include_in_global_results)
+    }
+
+    /// Lists the health of plugins whose name contains `filter` as results,
+    /// along with problems in the config, plugins with errors or timeouts first.
+    pub fn diagnostics(&self, filter: &str) -> Vec<QueryResult> {
+        let plugin: Arc<str> = "kal".into();
+        let filter = filter.to_lowercase();
+
+        let load_errors = self.load_errors.iter().map(|(name, error)| ResultItem {
+            id: format!("{}:{name}", Self::DIAGNOSTICS_COMMAND),
+            icon: crate::icon::BuiltinIcon::Error.into(),
+            primary_text: name.clone(),
+            secondary_text: format!("Failed to load: {error}"),
+            tooltip: Some(error.clone()),
+            actions: vec![],
+            score: 2,
+            highlights: Default::default(),
+            payload: serde_json::Value::Null,
+        });
+
+        let plugins = self.plugins.iter().map(PluginEntry::diagnostics_item);
+
+        plugins
+            .chain(load_errors)
+            .chain(self.config_diagnostics_items())
+            .filter(|item| item.primary_text.to_lowercase().contains(&filter))
+            .map(|item| {
+                let plugin = plugin.clone();
+                QueryResult { plugin, item }
+            })
+            .collect()
+    }
+
+    /// Lists the problems found in the config on the last reload as results, errors first.
+    pub fn config_diagnostics_items(&self) -> Vec<ResultItem> {
+        let mut items = self
+            .config_diagnostics
+            .iter()
+            .enumerate()
+            .map(|(i, diagnostic)| {
+                let (icon, score) = match diagnostic.severity {
+                    Severity::Error => (crate::icon::BuiltinIcon::Error, 2),
+                    Severity::Warning => (crate::icon::BuiltinIcon::Warning, 1),
+                };
+
+                let file = diagnostic.file.as_deref().and_then(Path::file_name);
+                let file = file.map(|f| f.to_string_lossy()).unwrap_or_default();
+                let location = match (diagnostic.line, diagnostic.column) {
+                    (Some(line), Some(column)) => format!("{file}, line {line}, column {column}"),
+                    _ => file.into_owned(),
+                };
+
+                ResultItem {
+                    id: format!("{}:config:{i}", Self::DIAGNOSTICS_COMMAND),
+                    icon: icon.into(),
+                    primary_text: diagnostic.message.clone(),
+                    secondary_text: location,
+                    tooltip: Some(diagnostic.to_string()),
+                    actions: vec![],
+                    score,
+                    highlights: Default::default(),
+                    payload: serde_json::Value::Null,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        items.sort_by(|a, b| b.score.cmp(&a.score));
+        items
+    }
+
+    /// Records the indexing progress `plugin` reported, see [`PluginStats::record_indexing`].
+    pub fn record_indexing(&self, plugin: &str, status: &IndexingStatus) {
+        if let Some(entry) = self.plugins.iter().find(|p| p.name() == plugin) {
+            entry.stats.lock().unwrap().record_indexing(status);
+        }
+    }
+
+    /// Runs [`Plugin::on_show`] of enabled plugins.
+    pub async fn on_show(&self) {
+        self.run_hook("on_show", |p| p.on_show()).await
+    }
+
+    /// Runs [`Plugin::on_hide`] of enabled plugins.
+    pub async fn on_hide(&self) {
+        self.run_hook("on_hide", |p| p.on_hide()).await
+    }
 
-            // queriable plugins are:
-            //   1. enabled
-            //   2. should be included in global results
-            let queriable_plugins = plugins
-                .iter_mut()
-                .filter(|p| p.enabled && p.include_in_global_results);
-
-            for plugin in queriable_plugins {
-                let result = plugin
-                    .query(trimmed_query, &mut matcher)
-                    .await
-                    .map_err(|e| plugin.error_item(e.to_string()));
-
-                match result {
-                    Ok(r) => r.extend_into(&mut results),
-                    Err(r) => results.push(r),
+    /// Runs [`Plugin::on_shutdown`] of enabled plugins.
+    pub async fn on_shutdown(&self) {
+        self.run_hook("on_shutdown", |p| p.on_shutdown()).await
+    }
+
+    /// Runs a lifecycle hook of enabled plugins concurrently, logging any errors.
+    async fn run_hook<'a, F>(&'a self, hook: &str, f: F)
+    where
+        F: Fn(&'a PluginEntry) -> BoxFuture<'a, anyhow::Result<()>>,
+    {
+        let hooks = self.plugins.iter().filter(|p| p.enabled).map(|p| {
+            let f = &f;
+            async move {
+                if let Err(e) = f(p).await {
+                    tracing::error!("Failed to run `{hook}` of `{}`: {e}", p.name());
+                }
+            }
+        });
+
+        futures::future::join_all(hooks).await;
+    }
+
+    /// `query` remapped through each pair of [`Self::keyboard_layouts`] both ways,
+    /// in case it was typed with the wrong layout active.
+    fn layout_rewrites(&self, query: &str) -> Vec<String> {
+        let mut rewrites = Vec::new();
+
+        for &[a, b] in &self.keyboard_layouts {
+            for (from, to) in [(a, b), (b, a)] {
+                let rewrite =
+                    kal_utils::remap_keyboard_layout(query, layout_keys(from), layout_keys(to));
+
+                if let Some(rewrite) = rewrite {
+                    if !rewrites.contains(&rewrite) {
+                        rewrites.push(rewrite);
+                    }
                 }
             }
         }
 
-        // sort results by scores in descending order
-        results.sort_by(|a, b| b.score.cmp(&a.score));
+        rewrites
+    }
+
+    /// Runs a [dispatched](kal_plugin::Action::dispatch) action of an item
+    /// produced by `plugin`, see [`Plugin::run_action`].
+    pub fn run_action(
+        &self,
+        plugin: &str,
+        action: &str,
+        payload: serde_json::Value,
+        progress: ActionProgress,
+    ) -> ActionFuture {
+        match self.plugins.iter().find(|p| p.name() == plugin) {
+            Some(entry) => entry.run_action(action, payload, progress),
+            None => {
+                let error = anyhow::anyhow!("Couldn't find plugin: {plugin}");
+                kal_plugin::ready_outcome::<ActionOutcome>(Err(error))
+            }
+        }
+    }
+}
+
+fn layout_keys(layout: KeyboardLayout) -> &'static [&'static str] {
+    match layout {
+        KeyboardLayout::English => &kal_utils::ENGLISH_KEYS,
+        KeyboardLayout::Russian => &kal_utils::RUSSIAN_KEYS,
+        KeyboardLayout::Arabic => &kal_utils::ARABIC_KEYS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kal_config::ConfigSchema;
+
+    use super::PluginManager;
+
+    #[test]
+    fn it_keeps_the_config_schema_up_to_date() {
+        let mut schema = ConfigSchema::new();
+        PluginManager::builtin_config_schemas(&mut schema);
+        let schema = serde_json::to_value(schema.generate()).unwrap();
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../kal-config/schema.json");
+        let json = std::fs::read_to_string(path).unwrap();
+        let checked_in = serde_json::from_str::<serde_json::Value>(&json).unwrap();
 
-        Ok(results)
+        assert!(
+            schema == checked_in,
+            "kal-config/schema.json is outdated, update it using `cargo run -- --config-schema kal-config/schema.json`"
+        );
     }
 }
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use kal_plugin::IndexingStatus;
+
+/// Health of a plugin, listed as results by the `kal:plugins` command so finding out
+/// why a plugin misbehaves doesn't require reading log files.
+#[derive(Debug, Default)]
+pub struct PluginStats {
+    /// How long the last reload took.
+    pub reload_duration: Option<Duration>,
+    /// The last error from reloading, querying or indexing, cleared on a successful reload.
+    pub last_error: Option<String>,
+    /// Latencies of the latest queries, oldest first.
+    latencies: VecDeque<Duration>,
+    /// Number of queries that timed out since the last reload.
+    pub timeouts: usize,
+    /// Number of results of the last query.
+    pub last_results: Option<usize>,
+    /// Number of items indexed, for plugins that index in the background.
+    pub indexed_items: Option<usize>,
+}
+
+impl PluginStats {
+    /// Max number of latencies kept for [`Self::latency_percentile`].
+    const MAX_LATENCIES: usize = 100;
+
+    pub fn record_reload(&mut self, duration: Duration, result: &anyhow::Result<()>) {
+        self.reload_duration = Some(duration);
+        self.last_error = result.as_ref().err().map(ToString::to_string);
+        self.timeouts = 0;
+    }
+
+    pub fn record_query(&mut self, latency: Duration, results: usize) {
+        self.push_latency(latency);
+        self.last_results = Some(results);
+    }
+
+    pub fn record_query_error(&mut self, latency: Duration, error: String) {
+        self.push_latency(latency);
+        self.last_error = Some(error);
+    }
+
+    pub fn record_timeout(&mut self, timeout: Duration) {
+        self.push_latency(timeout);
+        self.timeouts += 1;
+    }
+
+    pub fn record_indexing(&mut self, status: &IndexingStatus) {
+        match status {
+            IndexingStatus::Indexing { .. } => {}
+            IndexingStatus::Done { items } => self.indexed_items = Some(*items),
+            IndexingStatus::Failed { error } => self.last_error = Some(error.clone()),
+        }
+    }
+
+    fn push_latency(&mut self, latency: Duration) {
+        if self.latencies.len() == Self::MAX_LATENCIES {
+            self.latencies.pop_front();
+        }
+
+        self.latencies.push_back(latency);
+    }
+
+    /// Latency under which `percentile`% of the latest queries finished,
+    /// `None` if the plugin wasn't queried yet.
+    pub fn latency_percentile(&self, percentile: usize) -> Option<Duration> {
+        let mut latencies = self.latencies.iter().copied().collect::<Vec<_>>();
+        latencies.sort_unstable();
+
+        let idx = (latencies.len() * percentile)
+            .div_ceil(100)
+            .checked_sub(1)?;
+        latencies.get(idx).copied()
+    }
+}